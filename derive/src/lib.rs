@@ -0,0 +1,86 @@
+//! Derive macros for FunUTD.
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Error, Expr, Fields, LitStr};
+
+/// Derive `DnaChoice` for an enum with unit variants.
+/// Variants have weight 1 and are named after themselves by default.
+/// Use `#[dna(weight = 2.0)]` and `#[dna(name = "...")]` on variants to change them,
+/// and `#[dna(ordinal)]` on the enum if neighboring variants are similar.
+#[proc_macro_derive(DnaChoice, attributes(dna))]
+pub fn derive_dna_choice(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match dna_choice(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn dna_choice(input: &DeriveInput) -> Result<proc_macro2::TokenStream, Error> {
+    let Data::Enum(data) = &input.data else {
+        return Err(Error::new_spanned(
+            input,
+            "DnaChoice can only be derived for enums",
+        ));
+    };
+    if data.variants.is_empty() {
+        return Err(Error::new_spanned(
+            input,
+            "DnaChoice needs at least one variant",
+        ));
+    }
+    let mut ordinal = false;
+    for attribute in input.attrs.iter().filter(|a| a.path().is_ident("dna")) {
+        attribute.parse_nested_meta(|meta| {
+            if meta.path.is_ident("ordinal") {
+                ordinal = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `ordinal`"))
+            }
+        })?;
+    }
+    let mut choices = Vec::new();
+    let mut arms = Vec::new();
+    for (i, variant) in data.variants.iter().enumerate() {
+        if !matches!(variant.fields, Fields::Unit) {
+            return Err(Error::new_spanned(
+                variant,
+                "DnaChoice variants cannot have fields",
+            ));
+        }
+        let mut weight: Expr = syn::parse_quote!(1.0);
+        let mut name = LitStr::new(&variant.ident.to_string(), variant.ident.span());
+        for attribute in variant.attrs.iter().filter(|a| a.path().is_ident("dna")) {
+            attribute.parse_nested_meta(|meta| {
+                if meta.path.is_ident("weight") {
+                    weight = meta.value()?.parse()?;
+                    Ok(())
+                } else if meta.path.is_ident("name") {
+                    name = meta.value()?.parse()?;
+                    Ok(())
+                } else {
+                    Err(meta.error("expected `weight` or `name`"))
+                }
+            })?;
+        }
+        let ident = &variant.ident;
+        choices.push(quote! { ((#weight) as f32, #name) });
+        arms.push(quote! { #i => Self::#ident, });
+    }
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::funutd::dna::DnaChoice for #ident #type_generics #where_clause {
+            const CHOICES: &'static [(f32, &'static str)] = &[#(#choices),*];
+            const ORDINAL: bool = #ordinal;
+            fn from_index(i: usize) -> Self {
+                match i {
+                    #(#arms)*
+                    _ => panic!("DnaChoice index out of range"),
+                }
+            }
+        }
+    })
+}
@@ -5,41 +5,26 @@
 
 use eframe::egui;
 use funutd::prelude::*;
-use rayon::prelude::*;
-use std::fs::File;
-use std::io::BufWriter;
 use std::sync::mpsc;
 use std::thread;
 
-/// Convert texture value to u8. Canonical texture range is -1...1.
-pub fn convert_u8(x: f32) -> u8 {
-    ((x + 1.0) * (0.5 * 255.99999)).clamp(0.0, 255.99999) as u8
-}
-
-#[derive(Default, Clone)]
-struct Row {
-    pub origin: Vec3,
-    pub width: usize,
-    pub data: Vec<egui::Color32>,
-}
-
-impl Row {
-    pub fn point(&self, x: usize) -> Vec3 {
-        self.origin + vec3(x as f32 / self.width as f32, 0.0, 0.0)
-    }
+/// Generator configuration. Register custom nodes here to mix them into explored textures.
+fn gen_config() -> GenConfig {
+    GenConfig::default()
 }
 
 /// Number of render slots.
-const SLOTS: usize = 5;
-/// Number of render slots shown on-screen.
-const VISIBLE_SLOTS: usize = 4;
-/// Index of the export render slot.
-const EXPORT_SLOT: usize = 4;
+const SLOTS: usize = 4;
+/// Complexity of generated textures.
+const COMPLEXITY: f32 = 100.0;
+/// Number of rows rendered in one batch.
+const BATCH_ROWS: usize = 128;
+/// Size of the preview shown while exporting.
+const PREVIEW_SIZE: usize = 128;
 
 struct ImageMessage {
     pub slot: usize,
-    pub rows: usize,
-    pub image: Option<egui::ColorImage>,
+    pub image: egui::ColorImage,
 }
 
 struct RenderMessage {
@@ -61,8 +46,8 @@ struct RenderSlot {
     level: usize,
     /// Next row to render.
     row: usize,
-    /// All rows for current MIP level, rendered or not.
-    rows: Vec<Row>,
+    /// Pixels of the current MIP level rendered so far.
+    pixels: Vec<egui::Color32>,
 }
 
 impl RenderSlot {
@@ -73,7 +58,7 @@ impl RenderSlot {
             height: 1024,
             level: 4,
             row: 0,
-            rows: Vec::new(),
+            pixels: Vec::new(),
         }
     }
     fn level_width(&self) -> usize {
@@ -83,17 +68,12 @@ impl RenderSlot {
         self.height >> self.level
     }
     fn compute_image(&self) -> Option<egui::ColorImage> {
-        if self.row != self.rows.len() {
+        if self.row != self.level_height() {
             return None;
         }
-        assert!(self.rows.len() == self.level_height());
-        let mut pixels = Vec::with_capacity(self.level_width() * self.level_height());
-        for y in 0..self.rows.len() {
-            pixels.extend_from_slice(&self.rows[y].data[..]);
-        }
         Some(egui::ColorImage {
             size: [self.level_width(), self.level_height()],
-            pixels,
+            pixels: self.pixels.clone(),
         })
     }
     /// Sets the texture.
@@ -107,54 +87,44 @@ impl RenderSlot {
         self.texture = texture;
         self.width = width;
         self.height = height;
+        // Start from a finished level above the first level to render.
         self.level = levels;
-        self.row = 0;
-        self.rows = Vec::new();
+        self.row = self.level_height();
+        self.pixels = Vec::new();
     }
     /// Computes some more pixels. Returns whether we did any work.
     pub fn advance(&mut self) -> bool {
-        if self.row == self.rows.len() {
+        if self.row == self.level_height() {
             if self.level == 0 {
                 // Final state is a fully rendered texture.
                 return false;
             }
             self.row = 0;
             self.level -= 1;
-            self.rows.resize(self.level_height(), Row::default());
-            for i in (1..self.rows.len() >> 1).rev() {
-                self.rows.swap(i, i * 2);
-            }
-            for i in 0..self.rows.len() {
-                self.rows[i].width = self.level_width();
-                self.rows[i].origin = vec3(0.0, i as f32 / self.level_height() as f32, 0.5);
-                if !self.rows[i].data.is_empty() {
-                    self.rows[i]
-                        .data
-                        .resize(self.width >> self.level, egui::Color32::default());
-                    for j in (0..self.rows[i].width >> 1).rev() {
-                        self.rows[i].data[j * 2] = self.rows[i].data[j];
-                    }
-                }
-            }
+            self.pixels.clear();
         }
-        // Compute up to 128 rows in parallel.
-        let batch_rows = (self.rows.len() - self.row).min(128);
-        self.rows[self.row..self.row + batch_rows]
-            .par_iter_mut()
-            .for_each(|row| {
-                let is_progressive = !row.data.is_empty();
-                if !is_progressive {
-                    row.data.resize(row.width, egui::Color32::default());
-                }
-                for x in 0..row.width {
-                    if is_progressive && x & 1 == 0 {
-                        continue;
-                    }
-                    let v = self.texture.at(row.point(x).into());
-                    row.data[x] =
-                        egui::Color32::from_rgb(convert_u8(v.x), convert_u8(v.y), convert_u8(v.z));
-                }
-            });
+        // Render a band of rows. Rows are rendered in parallel.
+        let batch_rows = (self.level_height() - self.row).min(BATCH_ROWS);
+        let height = self.level_height() as f32;
+        let region = Region::new(
+            vec2(0.0, self.row as f32 / height),
+            vec2(1.0, (self.row + batch_rows) as f32 / height),
+        );
+        let image = render_2d(
+            &*self.texture,
+            self.level_width(),
+            batch_rows,
+            region,
+            0.5,
+            &RenderFormat::new(PixelFormat::Rgb8),
+        );
+        self.pixels.extend(
+            image
+                .as_u8()
+                .unwrap()
+                .chunks_exact(3)
+                .map(|c| egui::Color32::from_rgb(c[0], c[1], c[2])),
+        );
         self.row += batch_rows;
         true
     }
@@ -167,9 +137,20 @@ struct ImageSlot {
 }
 
 impl ImageSlot {
-    pub fn get_texture(&mut self, tiling_mode: TilingMode) -> Box<dyn Texture> {
+    /// Generate the texture of the slot with a material preset, or with the general generator
+    /// if no material is chosen.
+    pub fn get_texture(
+        &mut self,
+        material: Option<Material>,
+        tiling_mode: TilingMode,
+    ) -> Box<dyn Texture> {
         self.dna.reset();
-        genmap3palette(100.0, tiling_mode, &mut self.dna)
+        match material {
+            Some(material) => gen_material(material, tiling_mode, &mut self.dna),
+            // The editor configuration has no cost limit.
+            None => genmap3palette_config(COMPLEXITY, tiling_mode, &gen_config(), &mut self.dna)
+                .unwrap(),
+        }
     }
 }
 
@@ -197,22 +178,12 @@ fn main() {
                     if tx_image
                         .send(ImageMessage {
                             slot: slot_index,
-                            rows: image.height(),
-                            image: Some(image),
+                            image,
                         })
                         .is_err()
                     {
                         continue;
                     }
-                } else if tx_image
-                    .send(ImageMessage {
-                        slot: slot_index,
-                        rows: slot[slot_index].row,
-                        image: None,
-                    })
-                    .is_err()
-                {
-                    continue;
                 }
             } else {
                 no_progress += 1;
@@ -272,10 +243,13 @@ struct EditorApp {
     light_mode: bool,
     mutation_mode: MutationMode,
     tiling_mode: TilingMode,
+    material: Option<Material>,
     export_size: usize,
     export_path: std::path::PathBuf,
-    export_in_progress: bool,
-    export_rows: usize,
+    export_thread: Option<thread::JoinHandle<Result<(), SaveError>>>,
+    export_preview: Option<egui::TextureHandle>,
+    /// Message about the last load or export, shown below the buttons.
+    message: Option<String>,
     slot: Vec<ImageSlot>,
     focus_slot: usize,
     tx_render: mpsc::Sender<RenderMessage>,
@@ -292,10 +266,12 @@ impl EditorApp {
             light_mode: false,
             mutation_mode: MutationMode::Any,
             tiling_mode: TilingMode::All,
+            material: None,
             export_size: 4096,
             export_path: std::path::PathBuf::new(),
-            export_in_progress: false,
-            export_rows: 0,
+            export_thread: None,
+            export_preview: None,
+            message: None,
             slot: Vec::new(),
             focus_slot: 0,
             tx_render,
@@ -308,37 +284,38 @@ impl EditorApp {
                 dna,
                 texture: Box::new(zero()),
             };
-            if i < VISIBLE_SLOTS {
-                slot.texture = slot.get_texture(TilingMode::All);
-                if app
-                    .tx_render
-                    .send(RenderMessage {
-                        slot: i,
-                        width: 1024,
-                        height: 1024,
-                        levels: 4,
-                        texture: slot.get_texture(TilingMode::All),
-                    })
-                    .is_ok()
-                {}
-            }
+            slot.texture = slot.get_texture(None, TilingMode::All);
+            if app
+                .tx_render
+                .send(RenderMessage {
+                    slot: i,
+                    width: 1024,
+                    height: 1024,
+                    levels: 4,
+                    texture: slot.get_texture(None, TilingMode::All),
+                })
+                .is_ok()
+            {}
             app.slot.push(slot);
         }
         app
     }
     pub fn mutate(&mut self, source: usize) {
         self.focus_slot = source;
-        for mutate_i in 0..VISIBLE_SLOTS {
+        for mutate_i in 0..SLOTS {
             if mutate_i == source {
                 continue;
             }
             self.slot[mutate_i].dna = match self.mutation_mode {
                 MutationMode::Any => Dna::mutate(&self.slot[source].dna, self.rnd.u64(), 0.2),
-                MutationMode::Finetune => {
-                    Dna::finetune(&self.slot[source].dna, self.rnd.u64(), 0.2)
-                }
+                MutationMode::Finetune => Dna::mutate_with(
+                    &self.slot[source].dna,
+                    self.rnd.u64(),
+                    &Mutation::new(0.2).structural(false).sigma(0.05),
+                ),
             };
-            self.slot[mutate_i].texture = self.slot[mutate_i].get_texture(self.tiling_mode);
+            self.slot[mutate_i].texture =
+                self.slot[mutate_i].get_texture(self.material, self.tiling_mode);
             if self
                 .tx_render
                 .send(RenderMessage {
@@ -346,7 +323,7 @@ impl EditorApp {
                     width: 1024,
                     height: 1024,
                     levels: 4,
-                    texture: self.slot[mutate_i].get_texture(self.tiling_mode),
+                    texture: self.slot[mutate_i].get_texture(self.material, self.tiling_mode),
                 })
                 .is_ok()
             {}
@@ -354,7 +331,7 @@ impl EditorApp {
     }
     /// Call after altering one of the visible DNA slots.
     pub fn dna_updated(&mut self, slot: usize) {
-        self.slot[slot].texture = self.slot[slot].get_texture(self.tiling_mode);
+        self.slot[slot].texture = self.slot[slot].get_texture(self.material, self.tiling_mode);
         if self
             .tx_render
             .send(RenderMessage {
@@ -362,7 +339,7 @@ impl EditorApp {
                 width: 1024,
                 height: 1024,
                 levels: 4,
-                texture: self.slot[slot].get_texture(self.tiling_mode),
+                texture: self.slot[slot].get_texture(self.material, self.tiling_mode),
             })
             .is_ok()
         {}
@@ -377,40 +354,25 @@ impl eframe::App for EditorApp {
 
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         while let Ok(message) = self.rx_image.try_recv() {
-            if message.slot == EXPORT_SLOT {
-                if self.export_in_progress {
-                    self.export_rows = message.rows;
-                    if message.rows == self.export_size {
-                        self.export_in_progress = false;
-                        self.is_exporting = false;
-                        if let Ok(file) = File::create(self.export_path.clone()) {
-                            let writer = &mut BufWriter::new(file);
-
-                            let mut encoder = png::Encoder::new(
-                                writer,
-                                self.export_size as u32,
-                                self.export_size as u32,
-                            );
-                            encoder.set_color(png::ColorType::Rgb);
-                            encoder.set_depth(png::BitDepth::Eight);
-                            encoder.set_source_gamma(png::ScaledFloat::new(1.0 / 2.2));
-
-                            let mut writer = encoder.write_header().unwrap();
-
-                            let mut pixels: Vec<u8> = Vec::new();
-                            for color in message.image.unwrap().pixels {
-                                pixels.push(color.r());
-                                pixels.push(color.g());
-                                pixels.push(color.b());
-                            }
-                            writer.write_image_data(pixels.as_slice()).unwrap();
-                        }
-                    }
-                }
-            } else if let Some(image) = message.image {
-                self.slot[message.slot].image =
-                    Some(ctx.load_texture("", image, Default::default()));
-            }
+            self.slot[message.slot].image =
+                Some(ctx.load_texture("", message.image, Default::default()));
+        }
+        if self
+            .export_thread
+            .as_ref()
+            .is_some_and(|thread| thread.is_finished())
+        {
+            let thread = self.export_thread.take().unwrap();
+            self.message = match thread.join() {
+                Ok(Ok(())) => None,
+                Ok(Err(error)) => Some(format!(
+                    "Could not export {:?}: {}",
+                    self.export_path, error
+                )),
+                Err(_) => Some(format!("Could not export {:?}.", self.export_path)),
+            };
+            self.is_exporting = false;
+            self.export_preview = None;
         }
 
         ctx.set_visuals(if self.light_mode {
@@ -489,7 +451,24 @@ impl eframe::App for EditorApp {
                     ui.radio_value(&mut self.tiling_mode, TilingMode::XY, "XY");
                     ui.radio_value(&mut self.tiling_mode, TilingMode::All, "All");
                     if self.tiling_mode != previous_mode {
-                        for i in 0..VISIBLE_SLOTS {
+                        for i in 0..SLOTS {
+                            self.dna_updated(i);
+                        }
+                    }
+                });
+                ui.vertical(|ui| {
+                    ui.label("Generator");
+                    let previous_material = self.material;
+                    ui.radio_value(&mut self.material, None, "Any");
+                    for material in Material::ALL {
+                        ui.radio_value(
+                            &mut self.material,
+                            Some(material),
+                            format!("{:?}", material),
+                        );
+                    }
+                    if self.material != previous_material {
+                        for i in 0..SLOTS {
                             self.dna_updated(i);
                         }
                     }
@@ -502,7 +481,35 @@ impl eframe::App for EditorApp {
                 let dna = self.slot[self.focus_slot].dna.clone();
                 for parameter in dna.parameter_vector() {
                     ui.horizontal(|ui| {
-                        if !parameter.choices().is_empty() {
+                        let mut locked = dna.is_locked(parameter.hash());
+                        ui.push_id(id, |ui| {
+                            id += 1;
+                            if ui
+                                .checkbox(&mut locked, "")
+                                .on_hover_text("Lock parameter")
+                                .changed()
+                            {
+                                if locked {
+                                    self.slot[self.focus_slot].dna.lock(parameter.hash());
+                                } else {
+                                    self.slot[self.focus_slot].dna.unlock(parameter.hash());
+                                }
+                            }
+                        });
+                        if parameter.kind() == ParameterKind::Bool {
+                            let mut flag = parameter.value() == "true";
+                            if ui.checkbox(&mut flag, parameter.name()).changed() {
+                                let choice = if flag { "true" } else { "false" };
+                                // Choices with zero probability cannot be set.
+                                if self.slot[self.focus_slot]
+                                    .dna
+                                    .set_choice(parameter.hash(), choice)
+                                    .is_ok()
+                                {
+                                    self.dna_updated(self.focus_slot);
+                                }
+                            }
+                        } else if !parameter.choices().is_empty() {
                             ui.push_id(id, |ui| {
                                 id += 1;
                                 egui::ComboBox::from_label(parameter.name())
@@ -519,9 +526,10 @@ impl eframe::App for EditorApp {
                                                 )
                                                 .changed()
                                             {
-                                                self.slot[self.focus_slot]
-                                                    .dna
-                                                    .set_value(parameter.hash(), index as u32);
+                                                self.slot[self.focus_slot].dna.set_value(
+                                                    parameter.hash(),
+                                                    parameter.choice_indices()[index],
+                                                );
                                                 self.dna_updated(self.focus_slot);
                                             }
                                         }
@@ -529,6 +537,48 @@ impl eframe::App for EditorApp {
                             });
                         } else {
                             match parameter.kind() {
+                                ParameterKind::Vector | ParameterKind::Color => {
+                                    ui.label(parameter.name());
+                                    let mut value = parameter.value_vec3().unwrap();
+                                    if parameter.kind() == ParameterKind::Color {
+                                        let (r, g, b) = okhsl_to_srgb(value.x, value.y, value.z);
+                                        let color = egui::Color32::from_rgb(
+                                            (r * 255.0).round() as u8,
+                                            (g * 255.0).round() as u8,
+                                            (b * 255.0).round() as u8,
+                                        );
+                                        egui::widgets::color_picker::show_color(
+                                            ui,
+                                            color,
+                                            egui::vec2(16.0, 16.0),
+                                        );
+                                    }
+                                    let (minimum, maximum) = parameter.range().unwrap();
+                                    let mut changed = false;
+                                    for i in 0..3 {
+                                        ui.push_id(id, |ui| {
+                                            id += 1;
+                                            changed |= ui
+                                                .add(
+                                                    egui::Slider::new(
+                                                        &mut value[i],
+                                                        minimum[i]..=maximum[i],
+                                                    )
+                                                    .show_value(false),
+                                                )
+                                                .changed();
+                                        });
+                                    }
+                                    ui.label(parameter.value());
+                                    if changed
+                                        && self.slot[self.focus_slot]
+                                            .dna
+                                            .set_vec3(parameter.hash(), value)
+                                            .is_ok()
+                                    {
+                                        self.dna_updated(self.focus_slot);
+                                    }
+                                }
                                 ParameterKind::Ordered => {
                                     ui.label(parameter.name());
                                     let mut my_f32 = parameter.raw() as f32;
@@ -547,7 +597,7 @@ impl eframe::App for EditorApp {
                                         self.dna_updated(self.focus_slot);
                                     }
                                 }
-                                ParameterKind::Categorical => {
+                                ParameterKind::Categorical | ParameterKind::Bool => {
                                     ui.label(parameter.name());
                                     if parameter.maximum() > 100 {
                                         ui.label(parameter.value());
@@ -617,7 +667,7 @@ impl eframe::App for EditorApp {
                 }
 
                 if ui.button("Randomize All").clicked() {
-                    for i in 0..VISIBLE_SLOTS {
+                    for i in 0..SLOTS {
                         self.slot[i].dna = Dna::new(self.rnd.u64());
                         self.dna_updated(i);
                     }
@@ -634,33 +684,54 @@ impl eframe::App for EditorApp {
                         .set_directory("/")
                         .pick_file();
                     if let Some(path) = files {
-                        if let Ok(markup) = std::fs::read_to_string(path.as_path()) {
-                            let mut dna = Dna::new(Rnd::from_time().u64());
-                            for x in markup.lines() {
-                                if let Some(i) = x.find(' ') {
-                                    let key = &x[..i];
-                                    let value = &x[i + 1..];
-                                    if key.contains("TilingMode") {
-                                        if value.contains("All") {
-                                            self.tiling_mode = TilingMode::All;
-                                        } else if value.contains("None") {
-                                            self.tiling_mode = TilingMode::None;
-                                        } else {
-                                            self.tiling_mode = TilingMode::XY;
-                                        }
+                        match Dna::load(path.as_path()) {
+                            Ok((header, _)) if header.generator_version > GENERATOR_VERSION => {
+                                self.message = Some(format!(
+                                    "Could not load {:?}: generator version {} is newer than {}.",
+                                    path, header.generator_version, GENERATOR_VERSION
+                                ));
+                            }
+                            Ok((header, dna)) => {
+                                // Older genomes load, but their textures may have changed.
+                                self.message = if header.version == 0 {
+                                    Some(format!(
+                                        "Loaded {:?}, a legacy file without a generator version. \
+                                         The texture may differ from the saved one.",
+                                        path
+                                    ))
+                                } else if header.generator_version < GENERATOR_VERSION {
+                                    Some(format!(
+                                        "Loaded {:?} from generator version {}. \
+                                         The texture may differ from the saved one.",
+                                        path, header.generator_version
+                                    ))
+                                } else {
+                                    None
+                                };
+                                self.tiling_mode = header.tiling;
+                                self.material = Material::ALL
+                                    .into_iter()
+                                    .find(|material| material.generator_name() == header.generator);
+                                self.slot[self.focus_slot].dna =
+                                    if dna.hash_scheme() == HashScheme::Legacy {
+                                        Dna::migrate(&dna, |dna| {
+                                            genmap3palette_config(
+                                                COMPLEXITY,
+                                                header.tiling,
+                                                &gen_config(),
+                                                dna,
+                                            )
+                                            .unwrap()
+                                        })
                                     } else {
-                                        match (key.parse(), value.parse()) {
-                                            (Ok(key), Ok(value)) => {
-                                                dna.set_value(key, value);
-                                            }
-                                            _ => continue,
-                                        }
-                                    }
+                                        dna
+                                    };
+                                for i in 0..SLOTS {
+                                    self.dna_updated(i);
                                 }
                             }
-                            self.slot[self.focus_slot].dna = dna;
-                            for i in 0..VISIBLE_SLOTS {
-                                self.dna_updated(i);
+                            Err(error) => {
+                                self.message = Some(format!("Could not load {:?}: {}", path, error))
                             }
                         }
                     }
@@ -671,13 +742,25 @@ impl eframe::App for EditorApp {
                         .set_directory("/")
                         .save_file();
                     if let Some(path) = file {
-                        let _ = self.slot[self.focus_slot].dna.save(
-                            path.as_path(),
-                            &format!("TilingMode {:?}\n", self.tiling_mode),
+                        self.slot[self.focus_slot].dna.prune();
+                        let header = DnaHeader::new(
+                            self.material
+                                .map_or("genmap3palette", |material| material.generator_name()),
+                            GENERATOR_VERSION,
+                            COMPLEXITY,
+                            self.tiling_mode,
                         );
+                        if let Err(error) =
+                            self.slot[self.focus_slot].dna.save(path.as_path(), &header)
+                        {
+                            eprintln!("Could not save {:?}: {}", path, error);
+                        }
                     }
                 }
             });
+            if let Some(message) = &self.message {
+                ui.label(message.as_str());
+            }
             ui.code(code);
         });
 
@@ -704,23 +787,24 @@ impl eframe::App for EditorApp {
                 .collapsible(false)
                 .resizable(false)
                 .show(ctx, |ui| {
+                    let export_in_progress = self.export_thread.is_some();
                     let mut export_size = self.export_size as f32;
                     let response = ui.add(
                         egui::Slider::new(&mut export_size, 512.0..=16384.0)
                             .show_value(true)
                             .text("Size In Pixels"),
                     );
-                    if response.changed() && !self.export_in_progress {
+                    if response.changed() && !export_in_progress {
                         self.export_size = export_size.round() as usize;
                     }
                     ui.horizontal(|ui| {
                         let mut path_string: String =
                             self.export_path.to_str().unwrap_or("").into();
                         let text_response = ui.add(egui::TextEdit::singleline(&mut path_string));
-                        if text_response.changed() && !self.export_in_progress {
+                        if text_response.changed() && !export_in_progress {
                             self.export_path = std::path::PathBuf::from(path_string);
                         }
-                        if ui.add(egui::Button::new("..")).clicked() && !self.export_in_progress {
+                        if ui.add(egui::Button::new("..")).clicked() && !export_in_progress {
                             let files = rfd::FileDialog::new()
                                 .add_filter("PNG", &["png"])
                                 .set_directory("/")
@@ -732,37 +816,47 @@ impl eframe::App for EditorApp {
                         ui.add(egui::Label::new("File"));
                     });
                     ui.horizontal(|ui| {
-                        if ui.add(egui::Button::new("Export")).clicked() && !self.export_in_progress
-                        {
-                            self.export_in_progress = true;
-                            self.export_rows = 0;
-                            self.slot[EXPORT_SLOT].dna = self.slot[self.focus_slot].dna.clone();
-                            self.slot[EXPORT_SLOT].texture =
-                                self.slot[EXPORT_SLOT].get_texture(self.tiling_mode);
-                            self.slot[EXPORT_SLOT].image = None;
-
-                            if self
-                                .tx_render
-                                .send(RenderMessage {
-                                    slot: EXPORT_SLOT,
-                                    width: self.export_size,
-                                    height: self.export_size,
-                                    levels: 1,
-                                    texture: self.slot[EXPORT_SLOT].get_texture(self.tiling_mode),
-                                })
-                                .is_ok()
-                            {}
+                        if ui.add(egui::Button::new("Export")).clicked() && !export_in_progress {
+                            let texture = self.slot[self.focus_slot].texture.clone();
+                            // A small preview is rendered first and shown until the export is done.
+                            let preview = render_2d(
+                                &*texture,
+                                PREVIEW_SIZE,
+                                PREVIEW_SIZE,
+                                Region::unit(),
+                                0.5,
+                                &RenderFormat::new(PixelFormat::Rgb8),
+                            );
+                            let preview = egui::ColorImage {
+                                size: [PREVIEW_SIZE, PREVIEW_SIZE],
+                                pixels: preview
+                                    .as_u8()
+                                    .unwrap()
+                                    .chunks_exact(3)
+                                    .map(|c| egui::Color32::from_rgb(c[0], c[1], c[2]))
+                                    .collect(),
+                            };
+                            self.export_preview = Some(ctx.load_texture(
+                                "export preview",
+                                preview,
+                                Default::default(),
+                            ));
+                            let path = self.export_path.clone();
+                            let options = SaveOptions::new(self.export_size, self.export_size);
+                            self.export_thread =
+                                Some(thread::spawn(move || save_png(&*texture, &path, &options)));
                         }
                         if ui.add(egui::Button::new("Close")).clicked() {
                             self.is_exporting = !self.is_exporting;
                         }
-                        if self.export_in_progress {
-                            let bar = egui::ProgressBar::new(
-                                self.export_rows as f32 / self.export_size as f32,
-                            );
-                            ui.add(bar);
+                        if export_in_progress {
+                            ui.add(egui::Spinner::new());
                         }
                     });
+                    if let (true, Some(preview)) = (export_in_progress, &self.export_preview) {
+                        let image: egui::Image = preview.into();
+                        ui.add(image.fit_to_exact_size((240.0, 240.0).into()));
+                    }
                 });
         }
 
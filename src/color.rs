@@ -36,6 +36,16 @@ pub fn srgb_transfer_function(a: f32) -> f32 {
     }
 }
 
+/// Inverse of `srgb_transfer_function`: converts an sRGB encoded value to linear.
+pub fn srgb_transfer_function_inv(a: f32) -> f32 {
+    let a = clamp01(a);
+    if 0.04045 >= a {
+        a / 12.92
+    } else {
+        pow((a + 0.055) / 1.055, 2.4)
+    }
+}
+
 fn compute_max_saturation(a: f32, b: f32) -> f32 {
     let (k0, k1, k2, k3, k4, wl, wm, ws) = if -1.88170328 * a - 0.80936493 * b > 1.0 {
         (
@@ -194,9 +204,9 @@ fn find_gamut_intersection(
                 let u_b = b1 / (b1 * b1 - 0.5 * b * b2);
                 let t_b = -b * u_b;
 
-                let t_r = if u_r >= 0.0 { t_r } else { core::f32::INFINITY };
-                let t_g = if u_g >= 0.0 { t_g } else { core::f32::INFINITY };
-                let t_b = if u_b >= 0.0 { t_b } else { core::f32::INFINITY };
+                let t_r = if u_r >= 0.0 { t_r } else { f32::INFINITY };
+                let t_g = if u_g >= 0.0 { t_g } else { f32::INFINITY };
+                let t_b = if u_b >= 0.0 { t_b } else { f32::INFINITY };
 
                 t + min(t_r, min(t_g, t_b))
             }
@@ -492,6 +502,10 @@ impl Texture for Palette {
         vec3a(i.x * 2.0 - 1.0, i.y * 2.0 - 1.0, i.z * 2.0 - 1.0)
     }
 
+    fn estimated_cost(&self) -> f32 {
+        0.05 + self.texture.estimated_cost()
+    }
+
     fn get_code(&self) -> String {
         format!(
             "palette({:?}, {:?}, {:?}, {:?}, {:?}, {:?}, {:?}, {:?}, {:?}, {})",
@@ -32,6 +32,17 @@ impl Distance {
             Distance::NormMax => max(abs(vector.x), max(abs(vector.y), abs(vector.z))),
         }
     }
+    /// Estimated cost of a Voronoi basis with this metric, relative to gradient noise.
+    /// Cost depends on how many cells are scanned.
+    pub fn voronoi_cost(&self) -> f32 {
+        match self {
+            Distance::Norm1 => 6.3,
+            Distance::Norm2 => 2.75,
+            Distance::Norm4 => 1.7,
+            Distance::Norm8 => 1.6,
+            Distance::NormMax => 1.3,
+        }
+    }
     pub fn get_code(&self) -> String {
         format!("Distance::{:?}", self)
     }
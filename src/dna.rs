@@ -1,19 +1,99 @@
 //! Parameter system for procedural generation.
 
+use super::hash::*;
+use super::map3base::*;
 use super::math::*;
 use super::*;
 use core::hash::{BuildHasher, Hash, Hasher};
 use hashbrown::hash_map::DefaultHashBuilder;
-use hashbrown::HashMap;
-#[cfg(feature = "std")]
-use std::io::prelude::*;
+use hashbrown::{HashMap, HashSet};
 extern crate alloc;
 use alloc::{boxed::Box, string::String, string::ToString, vec::Vec};
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ParameterKind {
+    /// Raw values are indices or arbitrary numbers.
     Categorical,
+    /// Raw values are ordered and span the full `u32` range.
     Ordered,
+    /// Boolean flag with choices "false" and "true".
+    Bool,
+    /// Three component vector. Components are packed into the raw value.
+    Vector,
+    /// Color in Okhsl space. Components are packed into the raw value like vectors.
+    Color,
+}
+
+/// Largest raw values of vector components, which are packed into 11, 11 and 10 bits.
+const VECTOR_MAXIMUM: [u32; 3] = [0x7ff, 0x7ff, 0x3ff];
+
+/// Unpack the raw components of a vector parameter.
+fn unpack_vector(raw: u32) -> [u32; 3] {
+    [raw >> 21, (raw >> 10) & 0x7ff, raw & 0x3ff]
+}
+
+/// Pack raw vector components into a raw value.
+fn pack_vector(components: [u32; 3]) -> u32 {
+    (components[0] << 21) | (components[1] << 10) | components[2]
+}
+
+/// Mapping from 0...1 to parameter values. Recorded for parameters that have a real value.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Mapping {
+    /// Values are in 0...1.
+    Unit,
+    /// Values are in minimum...maximum.
+    Linear(f32, f32),
+    /// Values are in minimum...maximum on an exponential scale.
+    Exponential(f32, f32),
+    /// Values are in minimum...maximum, shaped by raising to a power.
+    Power(f32, f32, f32),
+    /// Values are transformed by an unknown function.
+    Custom,
+}
+
+impl Mapping {
+    /// Map x in 0...1 to a parameter value. Returns `None` for custom mappings.
+    pub fn forward(&self, x: f32) -> Option<f32> {
+        match *self {
+            Mapping::Unit => Some(x),
+            Mapping::Linear(minimum, maximum) => Some(lerp(minimum, maximum, x)),
+            Mapping::Exponential(minimum, maximum) => Some(xerp(minimum, maximum, x)),
+            Mapping::Power(minimum, maximum, exponent) => {
+                Some(lerp(minimum, maximum, pow(x, exponent)))
+            }
+            Mapping::Custom => None,
+        }
+    }
+
+    /// Map a parameter value back to 0...1. Returns `None` if the value is out of range
+    /// or the mapping is custom.
+    pub fn inverse(&self, value: f32) -> Option<f32> {
+        let x = match *self {
+            Mapping::Unit => value,
+            Mapping::Linear(minimum, maximum) => delerp(minimum, maximum, value),
+            Mapping::Exponential(minimum, maximum) => dexerp(minimum, maximum, value),
+            Mapping::Power(minimum, maximum, exponent) => {
+                pow(max(0.0, delerp(minimum, maximum, value)), 1.0 / exponent)
+            }
+            Mapping::Custom => return None,
+        };
+        // Allow for some rounding error at the ends of the range.
+        if (-1.0e-6..=1.0 + 1.0e-6).contains(&x) {
+            Some(clamp01(x))
+        } else {
+            None
+        }
+    }
+}
+
+/// Value awaiting a parameter to be drawn, which is when it can be converted to a raw value.
+#[derive(Clone, Debug)]
+enum Pending {
+    /// Double precision represents full range integers exactly.
+    Value(f64),
+    Choice(String),
+    Vector(Vec3),
 }
 
 /// Dna parameter. These are recorded for interactive and optimization purposes.
@@ -28,6 +108,13 @@ pub struct Parameter {
     raw: u32,
     hash: u64,
     choices: Vec<String>,
+    ordinal: bool,
+    mapping: Option<Mapping>,
+    choice_indices: Vec<u32>,
+    value_vec3: Option<Vec3>,
+    range: Option<(Vec3, Vec3)>,
+    /// Divisor that maps the raw value to 0...1.
+    scale: f32,
 }
 
 impl Parameter {
@@ -53,6 +140,12 @@ impl Parameter {
             raw,
             hash,
             choices,
+            ordinal: false,
+            mapping: None,
+            choice_indices: Vec::new(),
+            value_vec3: None,
+            range: None,
+            scale: maximum as f32,
         }
     }
     /// Parameter kind.
@@ -95,15 +188,346 @@ impl Parameter {
     pub fn choices(&self) -> &Vec<String> {
         &self.choices
     }
+    /// Whether raw values of a categorical parameter are ordered, so that neighboring values
+    /// are similar. Mutation of ordinal parameters steps to neighboring values.
+    pub fn is_ordinal(&self) -> bool {
+        self.ordinal
+    }
+    /// Mapping from raw values to real values, if applicable.
+    pub fn mapping(&self) -> Option<Mapping> {
+        self.mapping
+    }
+    /// Raw values of the choices, if applicable.
+    /// Choices with zero weight are omitted from the choice list,
+    /// so list position and raw value may differ.
+    pub fn choice_indices(&self) -> &Vec<u32> {
+        &self.choice_indices
+    }
+    /// Vector value of the parameter, if applicable.
+    /// For colors, the components are Okhsl hue, saturation and lightness.
+    pub fn value_vec3(&self) -> Option<Vec3> {
+        self.value_vec3
+    }
+    /// Minimum and maximum of vector components, if applicable.
+    pub fn range(&self) -> Option<(Vec3, Vec3)> {
+        self.range
+    }
 }
 
 const ADDRESS_LEVELS: usize = 8;
 
+/// Settings for `Dna::mutate_with`. By default, every unlocked parameter may be mutated.
+#[derive(Clone, Debug)]
+pub struct Mutation {
+    probability: f32,
+    sigma: Option<f32>,
+    structural: bool,
+    prefix: Option<Vec<u32>>,
+    names: Vec<String>,
+}
+
+impl Mutation {
+    /// Mutate each parameter with the given probability.
+    pub fn new(probability: f32) -> Self {
+        Self {
+            probability,
+            sigma: None,
+            structural: true,
+            prefix: None,
+            names: Vec::new(),
+        }
+    }
+
+    /// Mutation strength. Adjustments to ordered parameters are normally distributed
+    /// with standard deviation `sigma` (sigma > 0) relative to the range of the parameter.
+    /// Ordinal parameters step a similar distance, at least to an adjacent value.
+    /// By default, adjustments are drawn on an exponential scale that reaches up to the bounds,
+    /// and ordinal parameters are redrawn like other parameters.
+    pub fn sigma(mut self, sigma: f32) -> Self {
+        assert!(sigma > 0.0);
+        self.sigma = Some(sigma);
+        self
+    }
+
+    /// Whether structural parameters, which are parameters with choices, may be mutated.
+    /// The default is true.
+    pub fn structural(mut self, structural: bool) -> Self {
+        self.structural = structural;
+        self
+    }
+
+    /// Only mutate parameters whose address starts with `prefix`.
+    /// The address of a parameter is a prefix of the addresses of the parameters grouped under it.
+    pub fn prefix(mut self, prefix: &[u32]) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    /// Only mutate parameters whose name contains `name`.
+    /// If called repeatedly, parameters matching any of the names are mutated.
+    pub fn name(mut self, name: &str) -> Self {
+        self.names.push(name.into());
+        self
+    }
+
+    /// Whether mutation depends on information recorded in interactive mode.
+    fn is_restricted(&self) -> bool {
+        !self.structural || self.prefix.is_some() || !self.names.is_empty()
+    }
+
+    /// Whether the parameter passes the filters.
+    fn applies_to(&self, parameter: &Parameter) -> bool {
+        let in_prefix = match &self.prefix {
+            Some(prefix) => parameter.address().starts_with(prefix),
+            None => true,
+        };
+        (self.structural || parameter.choices().is_empty())
+            && in_prefix
+            && (self.names.is_empty() || self.names.iter().any(|x| parameter.name().contains(x)))
+    }
+}
+
+/// Version of the Dna file format written by `Dna::save` and `Dna::to_text`.
+/// Version 2 introduced stable hashing.
+pub const DNA_FORMAT_VERSION: u32 = 2;
+
+/// How parameter identities are hashed from tree addresses and parameter names.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HashScheme {
+    /// Names are hashed with the default hasher of `hashbrown`.
+    /// Results may change with dependency versions and target features.
+    /// Used in Dna files prior to format version 2.
+    Legacy,
+    /// Names are hashed with `hash_str`, which is fixed. The default.
+    /// Only the last 8 levels of the address are hashed, so parameters deep in the tree
+    /// may alias parameters in another branch.
+    Stable,
+    /// Like `Stable`, but the full address is hashed, which eliminates aliasing
+    /// between branches. Suitable for generators that build deep trees.
+    FullPath,
+}
+
+impl core::str::FromStr for HashScheme {
+    type Err = ();
+    /// Parses the variant name as formatted by `Debug`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Legacy" => Ok(HashScheme::Legacy),
+            "Stable" => Ok(HashScheme::Stable),
+            "FullPath" => Ok(HashScheme::FullPath),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Dna file header. Describes how the genome is to be interpreted.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DnaHeader {
+    /// File format version. Legacy files without a header are version 0.
+    pub version: u32,
+    /// Name of the generator the genome belongs to. Empty in legacy files.
+    pub generator: String,
+    /// Version of the generator. Zero in legacy files.
+    pub generator_version: u32,
+    /// Complexity the generator was called with. Zero in legacy files.
+    pub complexity: f32,
+    /// Tiling mode the generator was called with.
+    pub tiling: TilingMode,
+}
+
+impl DnaHeader {
+    /// Create a header in the current file format version.
+    pub fn new(
+        generator: &str,
+        generator_version: u32,
+        complexity: f32,
+        tiling: TilingMode,
+    ) -> Self {
+        Self {
+            version: DNA_FORMAT_VERSION,
+            generator: generator.into(),
+            generator_version,
+            complexity,
+            tiling,
+        }
+    }
+}
+
+/// Errors from reading and writing Dna files and setting parameter values.
+/// Line numbers start from 1.
+#[derive(Debug)]
+pub enum DnaError {
+    /// The file could not be read or written.
+    #[cfg(feature = "std")]
+    Io(std::io::Error),
+    /// The file format version is newer than supported.
+    UnsupportedVersion(u32),
+    /// Malformed or missing header line.
+    Header(usize),
+    /// Malformed gene line.
+    Gene(usize),
+    /// Gene is defined more than once.
+    DuplicateGene(usize),
+    /// No parameter with the hash has been recorded.
+    UnknownParameter(u64),
+    /// The parameter cannot be set by real value.
+    NotInvertible(u64),
+    /// The value is outside the range of the parameter.
+    OutOfRange(u64),
+    /// The parameter has no choice with the name.
+    UnknownChoice(u64),
+}
+
+impl core::fmt::Display for DnaError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            #[cfg(feature = "std")]
+            DnaError::Io(error) => write!(f, "I/O error: {}", error),
+            DnaError::UnsupportedVersion(version) => {
+                write!(f, "unsupported Dna format version {}", version)
+            }
+            DnaError::Header(line) => write!(f, "malformed header on line {}", line),
+            DnaError::Gene(line) => write!(f, "malformed gene on line {}", line),
+            DnaError::DuplicateGene(line) => write!(f, "duplicate gene on line {}", line),
+            DnaError::UnknownParameter(hash) => write!(f, "unknown parameter {}", hash),
+            DnaError::NotInvertible(hash) => {
+                write!(f, "parameter {} cannot be set by value", hash)
+            }
+            DnaError::OutOfRange(hash) => write!(f, "value out of range for parameter {}", hash),
+            DnaError::UnknownChoice(hash) => write!(f, "unknown choice for parameter {}", hash),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DnaError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DnaError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+/// Address path of a parameter, for example, `0/3/1`.
+fn address_path(address: &[u32]) -> String {
+    let mut path = String::new();
+    for (i, x) in address.iter().enumerate() {
+        if i > 0 {
+            path.push('/');
+        }
+        path.push_str(&format!("{}", x));
+    }
+    path
+}
+
+/// Difference in a parameter between two Dna, as reported by `Dna::diff`.
+/// Values are decoded values.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ParameterDiff {
+    /// Parameter is drawn only in the second Dna.
+    Added {
+        address: Vec<u32>,
+        name: String,
+        value: String,
+    },
+    /// Parameter is drawn only in the first Dna.
+    Removed {
+        address: Vec<u32>,
+        name: String,
+        value: String,
+    },
+    /// Parameter has a different raw value.
+    Changed {
+        address: Vec<u32>,
+        name: String,
+        from: String,
+        to: String,
+    },
+}
+
+impl core::fmt::Display for ParameterDiff {
+    /// Formats the difference in the style of readable Dna files,
+    /// for example, `~ 0/3/1 "basis" = Voronoi -> Worley`.
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ParameterDiff::Added {
+                address,
+                name,
+                value,
+            } => write!(f, "+ {} {:?} = {}", address_path(address), name, value),
+            ParameterDiff::Removed {
+                address,
+                name,
+                value,
+            } => write!(f, "- {} {:?} = {}", address_path(address), name, value),
+            ParameterDiff::Changed {
+                address,
+                name,
+                from,
+                to,
+            } => write!(
+                f,
+                "~ {} {:?} = {} -> {}",
+                address_path(address),
+                name,
+                from,
+                to
+            ),
+        }
+    }
+}
+
+/// Two parameters with the same hash drawn in one generation pass.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Collision {
+    /// The shared hash.
+    pub hash: u64,
+    /// Address of the parameter drawn first.
+    pub first_address: Vec<u32>,
+    /// Name of the parameter drawn first.
+    pub first_name: String,
+    /// Address of the parameter drawn second.
+    pub second_address: Vec<u32>,
+    /// Name of the parameter drawn second.
+    pub second_name: String,
+}
+
+/// How parameters are inherited from parents in `Dna::crossover`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CrossoverMode {
+    /// Each parameter is inherited from either parent with equal probability.
+    Uniform,
+    /// A random subtree of the first parent is replaced with parameters from the second parent.
+    /// The subtree consists of a parameter and all parameters grouped under it.
+    Subtree,
+    /// Parameters are inherited from the first parent up to a random point
+    /// in drawing order and from the second parent after it.
+    SinglePoint,
+}
+
+/// Enums that can be drawn as parameters with `Dna::enum_choice`.
+/// With the `derive` feature, implementations can be derived with `#[derive(DnaChoice)]`.
+/// Variants are weighted with `#[dna(weight = 2.0)]` and renamed with `#[dna(name = "...")]`.
+/// `#[dna(ordinal)]` on the enum marks the variants as ordered.
+pub trait DnaChoice: Sized {
+    /// Weights and names of the variants.
+    const CHOICES: &'static [(f32, &'static str)];
+    /// Whether neighboring variants are similar.
+    const ORDINAL: bool = false;
+    /// Variant at index `i` of `CHOICES`.
+    fn from_index(i: usize) -> Self;
+}
+
+#[cfg(feature = "derive")]
+pub use funutd_derive::DnaChoice;
+
 /// The Dna object contains the necessary, mutable
 /// context that is threaded through the generation process.
 /// Procedural generator parameter sets are tree shaped.
 /// The identity for each parameter is hashed from a local tree address and parameter name.
-/// Potential collisions are ignored.
+/// Potential collisions are ignored unless collision checking is enabled;
+/// see `Dna::set_collision_check`.
 /// We keep the current address inside Dna and update it as parameters are drawn.
 #[derive(Clone)]
 pub struct Dna {
@@ -119,6 +543,16 @@ pub struct Dna {
     interactive: bool,
     /// Drawn parameters for interactive display and editing.
     parameters: Vec<Parameter>,
+    /// How parameter hashes are calculated.
+    hash_scheme: HashScheme,
+    /// Hashes of parameters that are never mutated.
+    locks: HashSet<u64>,
+    /// Values set by real value before the parameter was drawn.
+    pending: HashMap<u64, Pending>,
+    /// Address and name of each parameter drawn in the current pass, if collision checking is on.
+    drawn: Option<HashMap<u64, (Vec<u32>, String)>>,
+    /// Collisions found in the current pass.
+    collisions: Vec<Collision>,
 }
 
 impl Dna {
@@ -131,14 +565,128 @@ impl Dna {
             rnd,
             interactive: true,
             parameters: Vec::new(),
+            hash_scheme: HashScheme::Stable,
+            locks: HashSet::new(),
+            pending: HashMap::new(),
+            drawn: None,
+            collisions: Vec::new(),
         }
     }
 
+    /// Enable or disable collision checking, which is off by default.
+    /// When on, parameters that hash to the same value in a generation pass are recorded
+    /// as collisions. Colliding parameters share a gene, so they are not independent.
+    /// Collisions can be eliminated with `HashScheme::FullPath`.
+    pub fn set_collision_check(&mut self, check: bool) {
+        self.drawn = if check { Some(HashMap::new()) } else { None };
+        self.collisions.clear();
+    }
+
+    /// Collisions found in the current generation pass if collision checking is on.
+    pub fn collisions(&self) -> &Vec<Collision> {
+        &self.collisions
+    }
+
+    /// Lock a parameter, which prevents it from being mutated.
+    pub fn lock(&mut self, hash: u64) {
+        self.locks.insert(hash);
+    }
+
+    /// Unlock a parameter.
+    pub fn unlock(&mut self, hash: u64) {
+        self.locks.remove(&hash);
+    }
+
+    /// Whether a parameter is locked.
+    pub fn is_locked(&self, hash: u64) -> bool {
+        self.locks.contains(&hash)
+    }
+
+    /// Parameter hashing scheme.
+    pub fn hash_scheme(&self) -> HashScheme {
+        self.hash_scheme
+    }
+
+    /// Set parameter hashing scheme. Existing genes are not rehashed; see `Dna::migrate`.
+    pub fn set_hash_scheme(&mut self, hash_scheme: HashScheme) {
+        self.hash_scheme = hash_scheme;
+    }
+
     /// Set the value of a gene.
     pub fn set_value(&mut self, hash: u64, value: u32) {
         self.genome.insert(hash, value);
     }
 
+    /// Convert a real value to a raw value in 0...maximum. The raw value is mapped to 0...1
+    /// by dividing with `scale`, as when drawing.
+    fn raw_from_value(mapping: &Mapping, maximum: u32, scale: f32, value: f32) -> Option<u32> {
+        let x = mapping.inverse(value)? as f64;
+        Some(clamp(0.0, maximum as f64, (x * scale as f64).round()) as u32)
+    }
+
+    /// Find a recorded parameter.
+    fn find_parameter(&self, hash: u64) -> Result<&Parameter, DnaError> {
+        self.parameters
+            .iter()
+            .find(|x| x.hash() == hash)
+            .ok_or(DnaError::UnknownParameter(hash))
+    }
+
+    /// Set a parameter by real value. The parameter must have been recorded in interactive mode
+    /// and its mapping must have an inverse. Takes effect when the generator is run again.
+    pub fn set_f32(&mut self, hash: u64, value: f32) -> Result<(), DnaError> {
+        let parameter = self.find_parameter(hash)?;
+        let mapping = parameter.mapping().ok_or(DnaError::NotInvertible(hash))?;
+        if mapping == Mapping::Custom {
+            return Err(DnaError::NotInvertible(hash));
+        }
+        let raw = Self::raw_from_value(&mapping, parameter.maximum(), parameter.scale, value)
+            .ok_or(DnaError::OutOfRange(hash))?;
+        self.set_value(hash, raw);
+        Ok(())
+    }
+
+    /// Set a parameter by choice name. The parameter must have been recorded in interactive mode.
+    /// Takes effect when the generator is run again.
+    pub fn set_choice(&mut self, hash: u64, choice: &str) -> Result<(), DnaError> {
+        let parameter = self.find_parameter(hash)?;
+        let i = parameter
+            .choices()
+            .iter()
+            .position(|x| x == choice)
+            .ok_or(DnaError::UnknownChoice(hash))?;
+        let raw = parameter.choice_indices()[i];
+        self.set_value(hash, raw);
+        Ok(())
+    }
+
+    /// Convert a vector value to a raw value. Returns `None` if a component is out of range.
+    fn raw_from_vec3(minimum: Vec3, maximum: Vec3, value: Vec3) -> Option<u32> {
+        let mut components = [0; 3];
+        for i in 0..3 {
+            let x = Mapping::Linear(minimum[i], maximum[i]).inverse(value[i])?;
+            components[i] = (x * VECTOR_MAXIMUM[i] as f32).round() as u32;
+        }
+        Some(pack_vector(components))
+    }
+
+    /// Set a vector or color parameter by value. The parameter must have been recorded
+    /// in interactive mode. Takes effect when the generator is run again.
+    pub fn set_vec3(&mut self, hash: u64, value: Vec3) -> Result<(), DnaError> {
+        let parameter = self.find_parameter(hash)?;
+        let (minimum, maximum) = parameter.range().ok_or(DnaError::NotInvertible(hash))?;
+        let raw = Self::raw_from_vec3(minimum, maximum, value).ok_or(DnaError::OutOfRange(hash))?;
+        self.set_value(hash, raw);
+        Ok(())
+    }
+
+    /// Number of values set in a readable Dna file without a raw value
+    /// that have not been matched with a parameter yet.
+    /// Values are matched when the generator is run.
+    pub fn unresolved(&self) -> usize {
+        self.pending.len()
+    }
+
     /// Parameters are recorded in interactive mode.
     pub fn is_interactive(&self) -> bool {
         self.interactive
@@ -164,75 +712,505 @@ impl Dna {
         &self.parameters
     }
 
-    /// Attempt to load Dna from the path.
-    #[cfg(feature = "std")]
-    pub fn load(path: &std::path::Path) -> Option<(String, Dna)> {
+    /// Header lines in the current file format.
+    fn header_text(&self, header: &DnaHeader) -> String {
+        format!(
+            "funutd dna {}\ngenerator {} {}\ncomplexity {:?}\ntiling {:?}\nhash {:?}\n",
+            DNA_FORMAT_VERSION,
+            header.generator,
+            header.generator_version,
+            header.complexity,
+            header.tiling,
+            self.hash_scheme
+        )
+    }
+
+    /// Serialize Dna into text in the current file format.
+    /// Genes are written in ascending order of hash.
+    pub fn to_text(&self, header: &DnaHeader) -> String {
+        let mut text = self.header_text(header);
+        let mut genes: Vec<(u64, u32)> = self.genome.iter().map(|(k, v)| (*k, *v)).collect();
+        genes.sort_unstable();
+        for (key, value) in genes {
+            text.push_str(&format!("{} {}\n", key, value));
+        }
+        text
+    }
+
+    /// Serialize Dna into human readable text. Requires interactive mode.
+    /// Parameters recorded during the last generation are written in drawing order, one per line,
+    /// as address path, name, decoded value and raw value, for example,
+    /// `0/3/1 "basis" = "Voronoi" ; raw 2`.
+    /// Parameters that share a gene with an earlier parameter are skipped.
+    /// The text can be read back with `Dna::from_text`. The raw value is used unless
+    /// the decoded value disagrees with it, for example, after editing, or the raw value
+    /// is omitted; then the decoded value is converted when the parameter is drawn.
+    pub fn to_readable_text(&self, header: &DnaHeader) -> String {
+        assert!(self.is_interactive());
+        let mut text = self.header_text(header);
+        // Parameters that collide share a gene, which is written once.
+        let mut written = HashSet::new();
+        for parameter in self.parameter_vector() {
+            if !written.insert(parameter.hash()) {
+                continue;
+            }
+            text.push_str(&address_path(parameter.address()));
+            if parameter.choices().is_empty() {
+                text.push_str(&format!(" {:?} = {}", parameter.name(), parameter.value()));
+            } else {
+                text.push_str(&format!(
+                    " {:?} = {:?}",
+                    parameter.name(),
+                    parameter.value()
+                ));
+            }
+            text.push_str(&format!(" ; raw {}\n", parameter.raw()));
+        }
+        text
+    }
+
+    /// Deserialize Dna from text. Legacy files without a header are accepted
+    /// and reported as format version 0.
+    pub fn from_text(text: &str) -> Result<(DnaHeader, Dna), DnaError> {
+        let mut dna = Dna::new(0);
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()));
+        let mut header = DnaHeader {
+            version: 0,
+            generator: String::new(),
+            generator_version: 0,
+            complexity: 0.0,
+            tiling: TilingMode::All,
+        };
+        // Files prior to version 2 always use the legacy scheme.
+        dna.hash_scheme = HashScheme::Legacy;
+        let mut first_gene = None;
+        match lines.next() {
+            Some((number, line)) => {
+                if let Some(version) = line.strip_prefix("funutd dna ") {
+                    header.version = version
+                        .trim()
+                        .parse()
+                        .map_err(|_| DnaError::Header(number))?;
+                    if header.version == 0 || header.version > DNA_FORMAT_VERSION {
+                        return Err(DnaError::UnsupportedVersion(header.version));
+                    }
+                    if header.version >= 2 {
+                        dna.hash_scheme = HashScheme::Stable;
+                    }
+                } else if let Some(tiling) = line.strip_prefix("TilingMode ") {
+                    // Legacy files start with a preamble, which the editor used for the tiling mode.
+                    header.tiling = tiling
+                        .trim()
+                        .parse()
+                        .map_err(|_| DnaError::Header(number))?;
+                } else if line.starts_with(|c: char| c.is_ascii_digit()) {
+                    first_gene = Some((number, line));
+                }
+            }
+            None => return Err(DnaError::Header(1)),
+        }
+        for (number, line) in first_gene.into_iter().chain(lines) {
+            if line.is_empty() {
+                continue;
+            }
+            if !line.starts_with(|c: char| c.is_ascii_digit()) {
+                if header.version == 0 {
+                    return Err(DnaError::Gene(number));
+                }
+                let (key, value) = line.split_once(' ').ok_or(DnaError::Header(number))?;
+                match key {
+                    "generator" => {
+                        // The name is empty in headers of legacy files.
+                        let (name, version) =
+                            value.rsplit_once(' ').ok_or(DnaError::Header(number))?;
+                        header.generator = name.trim().into();
+                        header.generator_version =
+                            version.parse().map_err(|_| DnaError::Header(number))?;
+                    }
+                    "complexity" => {
+                        header.complexity =
+                            value.trim().parse().map_err(|_| DnaError::Header(number))?;
+                    }
+                    "tiling" => {
+                        header.tiling =
+                            value.trim().parse().map_err(|_| DnaError::Header(number))?;
+                    }
+                    "hash" if header.version >= 2 => {
+                        dna.hash_scheme =
+                            value.trim().parse().map_err(|_| DnaError::Header(number))?;
+                    }
+                    _ => return Err(DnaError::Header(number)),
+                }
+                continue;
+            }
+            let (key, value) = line.split_once(' ').ok_or(DnaError::Gene(number))?;
+            let gene = if value.starts_with('"') {
+                Self::parse_readable_gene(dna.hash_scheme, key, value)
+            } else {
+                match (key.parse(), value.trim().parse()) {
+                    (Ok(key), Ok(value)) => Some((key, Some(value), None)),
+                    _ => None,
+                }
+            };
+            let (key, raw, pending) = gene.ok_or(DnaError::Gene(number))?;
+            if dna.genome.contains_key(&key) || dna.pending.contains_key(&key) {
+                return Err(DnaError::DuplicateGene(number));
+            }
+            if let Some(raw) = raw {
+                dna.genome.insert(key, raw);
+            }
+            if let Some(pending) = pending {
+                dna.pending.insert(key, pending);
+            }
+        }
+        Ok((header, dna))
+    }
+
+    /// Parse a quoted string, which must start at the beginning of `s`.
+    /// Escape sequences written by the `Debug` formatter of `str` are decoded.
+    /// Returns the string and the remainder of `s`.
+    fn parse_quoted(s: &str) -> Option<(String, &str)> {
+        let mut string = String::new();
+        let mut chars = s.char_indices();
+        if chars.next()?.1 != '"' {
+            return None;
+        }
+        loop {
+            match chars.next()? {
+                (_, '\\') => match chars.next()?.1 {
+                    'n' => string.push('\n'),
+                    'r' => string.push('\r'),
+                    't' => string.push('\t'),
+                    '0' => string.push('\0'),
+                    'u' => {
+                        if chars.next()?.1 != '{' {
+                            return None;
+                        }
+                        let mut code = 0u32;
+                        loop {
+                            match chars.next()?.1 {
+                                '}' => break,
+                                c => code = code.checked_mul(16)? + c.to_digit(16)?,
+                            }
+                        }
+                        string.push(char::from_u32(code)?);
+                    }
+                    c => string.push(c),
+                },
+                (i, '"') => return Some((string, &s[i + 1..])),
+                (_, c) => string.push(c),
+            }
+        }
+    }
+
+    /// Parse a gene written by `to_readable_text` into hash, raw value and decoded value.
+    /// The decoded value is returned as pending. Either value may be missing, but not both.
+    fn parse_readable_gene(
+        scheme: HashScheme,
+        path: &str,
+        rest: &str,
+    ) -> Option<(u64, Option<u32>, Option<Pending>)> {
+        let mut address = Vec::new();
+        for x in path.split('/') {
+            address.push(x.parse().ok()?);
+        }
+        let (name, rest) = Self::parse_quoted(rest)?;
+        let hash = Self::get_hash(scheme, &address, &name);
+        let value = rest.trim_start().strip_prefix('=')?;
+        match value.rsplit_once("; raw ") {
+            Some((value, raw)) => Some((
+                hash,
+                Some(raw.trim().parse().ok()?),
+                Self::parse_pending(value.trim()),
+            )),
+            None => Some((hash, None, Some(Self::parse_pending(value.trim())?))),
+        }
+    }
+
+    /// Parse a decoded value written by `to_readable_text`.
+    fn parse_pending(value: &str) -> Option<Pending> {
+        if value.starts_with('"') {
+            let (choice, rest) = Self::parse_quoted(value)?;
+            if !rest.trim().is_empty() {
+                return None;
+            }
+            Some(Pending::Choice(choice))
+        } else if let Some(vector) = value.strip_prefix('(') {
+            let mut components = vector.strip_suffix(')')?.split(',');
+            let mut v = Vec3::ZERO;
+            for i in 0..3 {
+                v[i] = components.next()?.trim().parse().ok()?;
+            }
+            if components.next().is_some() {
+                return None;
+            }
+            Some(Pending::Vector(v))
+        } else {
+            Some(Pending::Value(value.parse().ok()?))
+        }
+    }
+
+    /// Interpolate between Dna `a` and `b` (0 <= t <= 1). Requires interactive mode
+    /// and the same hash scheme in both. Ordered and ordinal parameters present in both
+    /// are interpolated in raw value space, which respects value mappings.
+    /// Other parameters switch from `a` to `b` at t = 0.5. Parameters present in only one
+    /// of the Dna are retained, so structural switches change between subtrees.
+    /// Missing parameters are drawn deterministically, which makes the result suitable
+    /// for animation.
+    pub fn interpolate(a: &Dna, b: &Dna, t: f32) -> Dna {
+        assert!(a.is_interactive() && b.is_interactive());
+        assert_eq!(a.hash_scheme, b.hash_scheme);
         let mut dna = Dna::new(0);
-        let mut is_first_line = true;
-        let mut preamble: String = String::new();
-        if let Ok(markup) = std::fs::read_to_string(path) {
-            for x in markup.lines() {
-                if is_first_line {
-                    preamble.push_str(x);
-                    is_first_line = false;
-                } else if let Some(i) = x.find(' ') {
-                    let key = x[..i].parse();
-                    let value = x[i + 1..].parse();
-                    match (key, value) {
-                        (Ok(key), Ok(value)) => {
-                            dna.genome.insert(key, value);
+        dna.hash_scheme = a.hash_scheme;
+        dna.locks = a.locks.union(&b.locks).copied().collect();
+        let (first, second) = if t < 0.5 { (b, a) } else { (a, b) };
+        // The preferred Dna is inserted last.
+        for parameter in first.parameter_vector() {
+            dna.set_value(parameter.hash(), parameter.raw());
+        }
+        for parameter in second.parameter_vector() {
+            dna.set_value(parameter.hash(), parameter.raw());
+        }
+        let b_parameters: HashMap<u64, &Parameter> =
+            b.parameter_vector().iter().map(|y| (y.hash(), y)).collect();
+        for x in a.parameter_vector() {
+            let y = match b_parameters.get(&x.hash()) {
+                Some(y) if x.kind() == y.kind() && x.maximum() == y.maximum() => y,
+                _ => continue,
+            };
+            match x.kind() {
+                ParameterKind::Vector | ParameterKind::Color => {
+                    let (xs, ys) = (unpack_vector(x.raw()), unpack_vector(y.raw()));
+                    let mut components = [0; 3];
+                    for i in 0..3 {
+                        let maximum = VECTOR_MAXIMUM[i] as f64;
+                        let (from, mut to) = (xs[i] as f64, ys[i] as f64);
+                        // Hue takes the shorter way around the color wheel.
+                        if x.kind() == ParameterKind::Color && i == 0 {
+                            if to - from > 0.5 * maximum {
+                                to -= maximum + 1.0;
+                            } else if from - to > 0.5 * maximum {
+                                to += maximum + 1.0;
+                            }
                         }
-                        _ => return None,
+                        let value = lerp(from, to, t as f64).round() as i64;
+                        components[i] = value.rem_euclid(VECTOR_MAXIMUM[i] as i64 + 1) as u32;
                     }
+                    dna.set_value(x.hash(), pack_vector(components));
+                }
+                _ if x.kind() == ParameterKind::Ordered || x.is_ordinal() => {
+                    let value = lerp(x.raw() as f64, y.raw() as f64, t as f64);
+                    dna.set_value(x.hash(), value.round() as u32);
                 }
+                _ => (),
             }
         }
-        Some((preamble, dna))
+        dna
+    }
+
+    /// Migrate the source Dna to the stable hash scheme.
+    /// The generator is run on a copy of the source to find out which parameters
+    /// the genome contains. Genes that are not drawn by the generator are dropped.
+    pub fn migrate<X, F: FnMut(&mut Dna) -> X>(source: &Dna, generator: F) -> Dna {
+        Dna::migrate_to(source, HashScheme::Stable, generator)
+    }
+
+    /// Migrate the source Dna to the given hash scheme. See `Dna::migrate`.
+    /// Parameters that collide in the source remain equal after migration.
+    pub fn migrate_to<X, F: FnMut(&mut Dna) -> X>(
+        source: &Dna,
+        scheme: HashScheme,
+        mut generator: F,
+    ) -> Dna {
+        let mut old = source.clone();
+        old.set_interactive(true);
+        old.reset();
+        generator(&mut old);
+        let mut dna = Dna::new(0);
+        dna.hash_scheme = scheme;
+        for parameter in old.parameter_vector() {
+            let hash = Self::get_hash(scheme, parameter.address(), parameter.name());
+            dna.set_value(hash, parameter.raw());
+            if old.is_locked(parameter.hash()) {
+                dna.lock(hash);
+            }
+        }
+        dna
+    }
+
+    /// Remove genes that were not drawn in the latest generation pass. Requires interactive mode.
+    /// Genes of inactive branches accumulate as structural parameters change,
+    /// for example, when mutating. Locks are kept.
+    /// Prune must follow a full generation pass; if nothing was drawn, for example,
+    /// right after loading, the genome is left as is.
+    pub fn prune(&mut self) {
+        assert!(self.is_interactive());
+        if self.parameters.is_empty() {
+            return;
+        }
+        let drawn: HashSet<u64> = self.parameters.iter().map(|x| x.hash()).collect();
+        self.genome.retain(|hash, _| drawn.contains(hash));
+    }
+
+    /// List parameters that differ between Dna `a` and `b`. Requires interactive mode.
+    /// Parameters drawn in both with a different raw value are changed;
+    /// they and added parameters are listed in the drawing order of `b`,
+    /// followed by removed parameters in the drawing order of `a`.
+    pub fn diff(a: &Dna, b: &Dna) -> Vec<ParameterDiff> {
+        assert!(a.is_interactive() && b.is_interactive());
+        let a_parameters: HashMap<u64, &Parameter> =
+            a.parameter_vector().iter().map(|x| (x.hash(), x)).collect();
+        let b_hashes: HashSet<u64> = b.parameter_vector().iter().map(|y| y.hash()).collect();
+        let mut diff = Vec::new();
+        for y in b.parameter_vector() {
+            match a_parameters.get(&y.hash()) {
+                Some(x) if x.raw() != y.raw() => diff.push(ParameterDiff::Changed {
+                    address: y.address().clone(),
+                    name: y.name().clone(),
+                    from: x.value().clone(),
+                    to: y.value().clone(),
+                }),
+                Some(_) => (),
+                None => diff.push(ParameterDiff::Added {
+                    address: y.address().clone(),
+                    name: y.name().clone(),
+                    value: y.value().clone(),
+                }),
+            }
+        }
+        for x in a.parameter_vector() {
+            if !b_hashes.contains(&x.hash()) {
+                diff.push(ParameterDiff::Removed {
+                    address: x.address().clone(),
+                    name: x.name().clone(),
+                    value: x.value().clone(),
+                });
+            }
+        }
+        diff
+    }
+
+    /// Load Dna from the path. Callers should check `generator_version` in the header:
+    /// genomes from a newer generator version may not reproduce their textures.
+    #[cfg(feature = "std")]
+    pub fn load(path: &std::path::Path) -> Result<(DnaHeader, Dna), DnaError> {
+        let text = std::fs::read_to_string(path).map_err(DnaError::Io)?;
+        Dna::from_text(&text)
     }
 
     /// Save Dna to the path.
     #[cfg(feature = "std")]
-    pub fn save(&self, path: &std::path::Path, preamble: &str) -> std::io::Result<()> {
-        let mut file = std::fs::File::create(path)?;
-        file.write_all(preamble.as_bytes())?;
-        for (key, value) in self.genome.iter() {
-            file.write_all(format!("{} {}\n", key, value).as_bytes())?;
-        }
-        Ok(())
+    pub fn save(&self, path: &std::path::Path, header: &DnaHeader) -> Result<(), DnaError> {
+        std::fs::write(path, self.to_text(header)).map_err(DnaError::Io)
+    }
+
+    /// Save Dna to the path in human readable form. Requires interactive mode.
+    /// The file can be loaded with `Dna::load`.
+    #[cfg(feature = "std")]
+    pub fn save_readable(
+        &self,
+        path: &std::path::Path,
+        header: &DnaHeader,
+    ) -> Result<(), DnaError> {
+        std::fs::write(path, self.to_readable_text(header)).map_err(DnaError::Io)
     }
 
     /// Mutate the source Dna. Return the mutated Dna.
     /// The probability of mutating each parameter is `mutation_p`.
     pub fn mutate(source: &Dna, seed: u64, mutation_p: f32) -> Dna {
+        Dna::mutate_with(source, seed, &Mutation::new(mutation_p))
+    }
+
+    /// Finetune the source Dna by only modifying non-structural parameters.
+    /// Requires interactive mode. Mutated parameters are redrawn; locked parameters are kept.
+    /// The probability of mutating each parameter is `mutation_p`.
+    pub fn finetune(source: &Dna, seed: u64, mutation_p: f32) -> Dna {
+        assert!(source.is_interactive());
         let mut rnd = Rnd::from_u64(seed);
         let mut dna = Dna::new(rnd.u64());
+        dna.hash_scheme = source.hash_scheme;
+        dna.locks.clone_from(&source.locks);
+        for parameter in source.parameter_vector() {
+            if !parameter.choices().is_empty()
+                || source.is_locked(parameter.hash())
+                || rnd.f32() >= mutation_p
+            {
+                dna.set_value(parameter.hash(), parameter.raw());
+            }
+        }
+        dna
+    }
+
+    /// Mutate the source Dna with the given settings. Return the mutated Dna.
+    /// Locked parameters are never mutated. Parameters whose values are drawn from
+    /// an ordered range are adjusted, ordinal parameters step to neighboring values
+    /// and other mutated parameters are redrawn.
+    /// Restricting mutation to structural, address or name filters requires interactive mode.
+    pub fn mutate_with(source: &Dna, seed: u64, mutation: &Mutation) -> Dna {
+        let mut rnd = Rnd::from_u64(seed);
+        let mut dna = Dna::new(rnd.u64());
+        dna.hash_scheme = source.hash_scheme;
+        dna.locks.clone_from(&source.locks);
         if source.is_interactive() {
             for parameter in source.parameter_vector() {
-                if rnd.f32() < mutation_p {
-                    if matches!(parameter.kind(), ParameterKind::Ordered) {
-                        let adjust = if rnd.bool(0.5) {
-                            xerp(
-                                1.0,
-                                max(1.0, parameter.maximum() as f64 - parameter.raw() as f64),
-                                rnd.f64(),
-                            )
+                if source.is_locked(parameter.hash())
+                    || !mutation.applies_to(parameter)
+                    || rnd.f32() >= mutation.probability
+                {
+                    dna.set_value(parameter.hash(), parameter.raw());
+                } else if matches!(parameter.kind(), ParameterKind::Ordered) {
+                    let value =
+                        Self::adjust(&mut rnd, mutation, parameter.raw(), parameter.maximum());
+                    dna.set_value(parameter.hash(), value);
+                } else if matches!(
+                    parameter.kind(),
+                    ParameterKind::Vector | ParameterKind::Color
+                ) {
+                    let mut components = unpack_vector(parameter.raw());
+                    for (i, x) in components.iter_mut().enumerate() {
+                        if parameter.kind() == ParameterKind::Color && i == 0 {
+                            // Hue wraps around, so it is stepped up to half a turn either way.
+                            let period = VECTOR_MAXIMUM[i] as f64 + 1.0;
+                            let step = if let Some(sigma) = mutation.sigma {
+                                rnd.normal() * sigma as f64 * period
+                            } else if rnd.bool(0.5) {
+                                xerp(1.0, 0.5 * period, rnd.f64())
+                            } else {
+                                -xerp(1.0, 0.5 * period, rnd.f64())
+                            };
+                            let value = (*x as f64 + step).round() as i64;
+                            *x = value.rem_euclid(period as i64) as u32;
                         } else {
-                            -xerp(1.0, max(1.0, parameter.raw() as f64), rnd.f64())
-                        };
-                        let value = clamp(
-                            0.0,
-                            parameter.maximum() as f64,
-                            parameter.raw() as f64 + adjust,
-                        );
-                        dna.set_value(parameter.hash(), value.round() as u32);
+                            *x = Self::adjust(&mut rnd, mutation, *x, VECTOR_MAXIMUM[i]);
+                        }
                     }
-                } else {
-                    dna.set_value(parameter.hash(), parameter.raw());
+                    dna.set_value(parameter.hash(), pack_vector(components));
+                } else if let (Some(sigma), true) = (
+                    mutation.sigma,
+                    parameter.is_ordinal() && parameter.maximum() > 0,
+                ) {
+                    // Choices step over the choices with positive weight only.
+                    let allowed = parameter.choice_indices();
+                    let value = if allowed.is_empty() {
+                        Self::step(&mut rnd, sigma, parameter.raw(), parameter.maximum())
+                    } else {
+                        let i = allowed
+                            .iter()
+                            .position(|x| *x == parameter.raw())
+                            .unwrap_or(0);
+                        allowed[Self::step(&mut rnd, sigma, i as u32, allowed.len() as u32 - 1)
+                            as usize]
+                    };
+                    dna.set_value(parameter.hash(), value);
                 }
             }
         } else {
+            assert!(!mutation.is_restricted());
             for (parameter_hash, source_value) in source.genome.iter() {
-                if rnd.f32() >= mutation_p {
+                if source.is_locked(*parameter_hash) || rnd.f32() >= mutation.probability {
                     dna.set_value(*parameter_hash, *source_value);
                 }
             }
@@ -240,22 +1218,113 @@ impl Dna {
         dna
     }
 
-    /// Finetune the source Dna by only modifying non-structural parameters.
-    /// Requires interactive mode.
-    /// The probability of mutating each parameter is `mutation_p`.
-    pub fn finetune(source: &Dna, seed: u64, mutation_p: f32) -> Dna {
-        assert!(source.is_interactive());
+    /// Step an ordinal raw value in 0...maximum by a normally distributed amount
+    /// with standard deviation `sigma` relative to the range, at least to a neighbor.
+    fn step(rnd: &mut Rnd, sigma: f32, raw: u32, maximum: u32) -> u32 {
+        if maximum == 0 {
+            return raw;
+        }
+        let (raw, maximum) = (raw as i64, maximum as i64);
+        let mut step = (rnd.normal() * sigma as f64 * (maximum + 1) as f64).round() as i64;
+        // Step to a neighbor at least.
+        if step == 0 {
+            step = if rnd.bool(0.5) { 1 } else { -1 };
+        }
+        // Reflect steps that go out of bounds.
+        let mut value = if raw + step < 0 || raw + step > maximum {
+            raw - step
+        } else {
+            raw + step
+        }
+        .clamp(0, maximum);
+        // If the step was too large to reflect, choose any other value.
+        if value == raw {
+            value = rnd.u64_to(maximum as u64) as i64;
+            if value >= raw {
+                value += 1;
+            }
+        }
+        value as u32
+    }
+
+    /// Adjust an ordered raw value in 0...maximum.
+    fn adjust(rnd: &mut Rnd, mutation: &Mutation, raw: u32, maximum: u32) -> u32 {
+        let maximum = maximum as f64;
+        let raw = raw as f64;
+        let adjust = if let Some(sigma) = mutation.sigma {
+            rnd.normal() * sigma as f64 * maximum
+        } else if rnd.bool(0.5) {
+            xerp(1.0, max(1.0, maximum - raw), rnd.f64())
+        } else {
+            -xerp(1.0, max(1.0, raw), rnd.f64())
+        };
+        clamp(0.0, maximum, raw + adjust).round() as u32
+    }
+
+    /// Cross over parents `a` and `b`. Return the offspring Dna.
+    /// Requires interactive mode and the same hash scheme in both parents.
+    /// Parameters present in only one parent are inherited from that parent.
+    pub fn crossover(a: &Dna, b: &Dna, seed: u64, mode: CrossoverMode) -> Dna {
+        assert!(a.is_interactive() && b.is_interactive());
+        assert_eq!(a.hash_scheme, b.hash_scheme);
         let mut rnd = Rnd::from_u64(seed);
         let mut dna = Dna::new(rnd.u64());
-        for parameter in source.parameter_vector() {
-            if !parameter.choices().is_empty() || rnd.f32() >= mutation_p {
-                dna.set_value(parameter.hash(), parameter.raw());
+        dna.hash_scheme = a.hash_scheme;
+        dna.locks = a.locks.union(&b.locks).copied().collect();
+        for parameter in b.parameter_vector() {
+            dna.set_value(parameter.hash(), parameter.raw());
+        }
+        match mode {
+            CrossoverMode::Uniform => {
+                for parameter in a.parameter_vector() {
+                    if rnd.bool(0.5) || !dna.genome.contains_key(&parameter.hash()) {
+                        dna.set_value(parameter.hash(), parameter.raw());
+                    }
+                }
+            }
+            CrossoverMode::Subtree => {
+                // Prefer structural parameters as subtree roots.
+                let mut roots: Vec<&Parameter> = a
+                    .parameter_vector()
+                    .iter()
+                    .filter(|x| !x.choices().is_empty())
+                    .collect();
+                if roots.is_empty() {
+                    roots = a.parameter_vector().iter().collect();
+                }
+                let root = if roots.is_empty() {
+                    None
+                } else {
+                    Some(
+                        roots[rnd.u64_to(roots.len() as u64) as usize]
+                            .address()
+                            .clone(),
+                    )
+                };
+                let in_subtree = |parameter: &Parameter| match &root {
+                    Some(root) => parameter.address().starts_with(root),
+                    None => false,
+                };
+                // Parameters of the subtree that are missing from `b` are inherited from `a`.
+                for parameter in a.parameter_vector() {
+                    if !in_subtree(parameter) || !dna.genome.contains_key(&parameter.hash()) {
+                        dna.set_value(parameter.hash(), parameter.raw());
+                    }
+                }
+            }
+            CrossoverMode::SinglePoint => {
+                let point = rnd.u64_to(a.parameters() as u64 + 1) as usize;
+                for (i, parameter) in a.parameter_vector().iter().enumerate() {
+                    if i < point || !dna.genome.contains_key(&parameter.hash()) {
+                        dna.set_value(parameter.hash(), parameter.raw());
+                    }
+                }
             }
         }
         dna
     }
 
-    /// Add a parameter.
+    /// Add a parameter. Returns the parameter for setting further properties.
     fn add_parameter(
         &mut self,
         kind: ParameterKind,
@@ -267,31 +1336,64 @@ impl Dna {
         raw: u32,
         hash: u64,
         choices: Vec<String>,
-    ) {
+    ) -> &mut Parameter {
         self.parameters.push(Parameter::new(
             kind, name, value, value_f32, address, maximum, raw, hash, choices,
         ));
+        self.parameters.last_mut().unwrap()
     }
 
-    /// Calculate the current address hash based on our tree location.
-    fn get_address_hash(&self) -> u64 {
-        let l = self.address.len();
-        let n = min(ADDRESS_LEVELS, l);
+    /// Calculate an address hash from the last `levels` levels of a tree location.
+    fn get_address_hash(address: &[u32], levels: usize) -> u64 {
+        let l = address.len();
+        let n = min(levels, l);
         let mut hash: u64 = n as u64;
         // Use an ad hoc hash.
-        for i in (l - n)..l {
-            hash = (hash ^ self.address[i] as u64 ^ (hash >> 32)).wrapping_mul(0xd6e8feb86659fd93);
+        for x in &address[l - n..] {
+            hash = (hash ^ *x as u64 ^ (hash >> 32)).wrapping_mul(0xd6e8feb86659fd93);
         }
         (hash ^ (hash >> 32)).wrapping_mul(0xd6e8feb86659fd93)
     }
 
-    /// Calculate a parameter hash based on our tree location and parameter name.
+    /// Calculate a parameter hash from a tree location and parameter name.
     #[allow(clippy::manual_hash_one)]
-    fn get_parameter_hash(&self, parameter_name: &str) -> u64 {
-        let address_hash = self.get_address_hash();
-        let mut hasher = DefaultHashBuilder::default().build_hasher();
-        parameter_name.hash(&mut hasher);
-        hasher.finish() ^ address_hash
+    fn get_hash(scheme: HashScheme, address: &[u32], parameter_name: &str) -> u64 {
+        let levels = match scheme {
+            HashScheme::FullPath => address.len(),
+            _ => ADDRESS_LEVELS,
+        };
+        let address_hash = Self::get_address_hash(address, levels);
+        match scheme {
+            HashScheme::Legacy => {
+                let mut hasher = DefaultHashBuilder::default().build_hasher();
+                parameter_name.hash(&mut hasher);
+                hasher.finish() ^ address_hash
+            }
+            HashScheme::Stable | HashScheme::FullPath => {
+                hash128a(((address_hash as u128) << 64) | hash_str(parameter_name) as u128)
+            }
+        }
+    }
+
+    /// Calculate a parameter hash based on our tree location and parameter name.
+    /// Checks for collisions if enabled.
+    fn get_parameter_hash(&mut self, parameter_name: &str) -> u64 {
+        let hash = Self::get_hash(self.hash_scheme, &self.address, parameter_name);
+        if let Some(drawn) = &mut self.drawn {
+            match drawn.get(&hash) {
+                Some((address, name)) => self.collisions.push(Collision {
+                    hash,
+                    first_address: address.clone(),
+                    first_name: name.clone(),
+                    second_address: self.address.clone(),
+                    second_name: parameter_name.into(),
+                }),
+                None => {
+                    drawn.insert(hash, (self.address.clone(), parameter_name.into()));
+                }
+            }
+        }
+        hash
     }
 
     /// Draw a parameter value. Adjusts current tree address.
@@ -316,11 +1418,20 @@ impl Dna {
     pub fn reset(&mut self) {
         self.address = vec![0];
         self.parameters.clear();
+        if let Some(drawn) = &mut self.drawn {
+            drawn.clear();
+        }
+        self.collisions.clear();
     }
 
     /// Returns a full range u32 parameter.
     pub fn u32(&mut self, name: &str) -> u32 {
         let hash = self.get_parameter_hash(name);
+        if let Some(Pending::Value(x)) = self.pending.remove(&hash) {
+            if x == x.round() && x >= 0.0 && x <= u32::MAX as f64 {
+                self.genome.insert(hash, x as u32);
+            }
+        }
         let value = self.draw_value(hash);
         if self.is_interactive() {
             self.add_parameter(
@@ -342,10 +1453,20 @@ impl Dna {
     /// Returns a u32 parameter in the given inclusive range.
     pub fn u32_in(&mut self, name: &str, minimum: u32, maximum: u32) -> u32 {
         let hash = self.get_parameter_hash(name);
+        if let Some(Pending::Value(x)) = self.pending.remove(&hash) {
+            let x = x.round();
+            let agrees = match self.genome.get(&hash) {
+                Some(raw) => (raw % (maximum - minimum + 1) + minimum) as f64 == x,
+                None => false,
+            };
+            if !agrees && x >= minimum as f64 && x <= maximum as f64 {
+                self.genome.insert(hash, x as u32 - minimum);
+            }
+        }
         let value = self.draw_value(hash);
         let value = value % (maximum - minimum + 1);
         if self.is_interactive() {
-            self.add_parameter(
+            let parameter = self.add_parameter(
                 ParameterKind::Categorical,
                 name.into(),
                 format!("{:?}", value + minimum),
@@ -356,18 +1477,40 @@ impl Dna {
                 hash,
                 Vec::new(),
             );
+            parameter.ordinal = true;
+            parameter.mapping = Some(Mapping::Linear(minimum as f32, maximum as f32));
         }
         self.advance();
         value + minimum
     }
 
-    /// Returns an f32 parameter in 0...1.
-    pub fn f32(&mut self, name: &str) -> f32 {
+    /// Returns an f32 parameter. The raw value is divided by `scale` and then transformed
+    /// by `xform`, which is described by `mapping`.
+    fn draw_f32<T: Fn(f32) -> f32>(
+        &mut self,
+        name: &str,
+        mapping: Mapping,
+        scale: f32,
+        xform: T,
+    ) -> f32 {
         let hash = self.get_parameter_hash(name);
+        if let Some(Pending::Value(x)) = self.pending.remove(&hash) {
+            let x = x as f32;
+            // Values agree if they are equal at the displayed precision.
+            let agrees = match self.genome.get(&hash) {
+                Some(raw) => format!("{0:.3}", xform(*raw as f32 / scale)) == format!("{0:.3}", x),
+                None => false,
+            };
+            if !agrees {
+                if let Some(raw) = Self::raw_from_value(&mapping, 0xffffffff, scale, x) {
+                    self.genome.insert(hash, raw);
+                }
+            }
+        }
         let value = self.draw_value(hash);
-        let value_f = value as f32 / ((1u64 << 32) as f32);
+        let value_f = xform(value as f32 / scale);
         if self.is_interactive() {
-            self.add_parameter(
+            let parameter = self.add_parameter(
                 ParameterKind::Ordered,
                 name.into(),
                 format!("{0:.3}", value_f),
@@ -378,66 +1521,90 @@ impl Dna {
                 hash,
                 Vec::new(),
             );
+            parameter.mapping = Some(mapping);
+            parameter.scale = scale;
         }
         self.advance();
         value_f
     }
 
+    /// Returns an f32 parameter in 0...1.
+    pub fn f32(&mut self, name: &str) -> f32 {
+        self.draw_f32(name, Mapping::Unit, (1u64 << 32) as f32, |x| x)
+    }
+
     /// Returns an f32 parameter in minimum...maximum.
     pub fn f32_in(&mut self, name: &str, minimum: f32, maximum: f32) -> f32 {
-        let hash = self.get_parameter_hash(name);
-        let value = self.draw_value(hash);
-        let value_f = lerp(minimum, maximum, value as f32 / (((1u64 << 32) - 1) as f32));
-        if self.is_interactive() {
-            self.add_parameter(
-                ParameterKind::Ordered,
-                name.into(),
-                format!("{0:.3}", value_f),
-                Some(value_f),
-                self.address.clone(),
-                0xffffffff,
-                value,
-                hash,
-                Vec::new(),
-            );
-        }
-        self.advance();
-        value_f
+        self.draw_f32(
+            name,
+            Mapping::Linear(minimum, maximum),
+            ((1u64 << 32) - 1) as f32,
+            |x| lerp(minimum, maximum, x),
+        )
+    }
+
+    /// Returns an f32 parameter in minimum...maximum (minimum > 0, maximum > 0)
+    /// distributed on an exponential scale.
+    pub fn f32_xerp(&mut self, name: &str, minimum: f32, maximum: f32) -> f32 {
+        self.draw_f32(
+            name,
+            Mapping::Exponential(minimum, maximum),
+            (1u64 << 32) as f32,
+            |x| xerp(minimum, maximum, x),
+        )
+    }
+
+    /// Returns an f32 parameter in minimum...maximum shaped by raising
+    /// a value in 0...1 to the power `exponent` (exponent > 0).
+    pub fn f32_pow(&mut self, name: &str, minimum: f32, maximum: f32, exponent: f32) -> f32 {
+        self.draw_f32(
+            name,
+            Mapping::Power(minimum, maximum, exponent),
+            (1u64 << 32) as f32,
+            |x| lerp(minimum, maximum, pow(x, exponent)),
+        )
     }
 
     /// Returns an f32 parameter transformed by the supplied function.
+    /// The transform is not recorded, so these parameters cannot be set by value.
     pub fn f32_xform<T: Fn(f32) -> f32>(&mut self, name: &str, xform: T) -> f32 {
-        let hash = self.get_parameter_hash(name);
-        let value = self.draw_value(hash);
-        let value_f = xform(value as f32 / ((1u64 << 32) as f32));
-        if self.is_interactive() {
-            self.add_parameter(
-                ParameterKind::Ordered,
-                name.into(),
-                format!("{0:.3}", value_f),
-                Some(value_f),
-                self.address.clone(),
-                0xffffffff,
-                value,
-                hash,
-                Vec::new(),
-            );
-        }
-        self.advance();
-        value_f
+        self.draw_f32(name, Mapping::Custom, (1u64 << 32) as f32, xform)
     }
 
-    /// Returns the index of a choice.
-    pub fn index<const T: usize>(&mut self, name: &str, choices: [(f32, &str); T]) -> u32 {
+    /// Returns the index of a choice. Choices are ordinal if `ordinal` is set.
+    fn draw_index(
+        &mut self,
+        name: &str,
+        choices: &[(f32, &str)],
+        kind: ParameterKind,
+        ordinal: bool,
+    ) -> usize {
         let hash = self.get_parameter_hash(name);
+        if let Some(Pending::Choice(choice)) = self.pending.remove(&hash) {
+            if let Some(i) = choices
+                .iter()
+                .position(|(weight, name)| *weight > 0.0 && *name == choice)
+            {
+                self.genome.insert(hash, i as u32);
+            }
+        }
         let value = self.draw_value(hash);
+        // Only choices with positive weight can be selected. The last one is a fallback
+        // for rounding errors.
+        let last_allowed = choices
+            .iter()
+            .rposition(|(weight, _)| *weight > 0.0)
+            .expect("at least one choice must have positive weight");
         let choice_index = if (value as usize) < choices.len() && choices[value as usize].0 > 0.0 {
             value as usize
         } else {
-            let total_weight: f32 = choices.iter().map(|(weight, _)| weight).sum();
+            let total_weight: f32 = choices.iter().map(|(weight, _)| weight.max(0.0)).sum();
             let mut value = value as f32 / ((1u64 << 32) as f32) * total_weight;
-            let mut choice_index = 0;
+            let mut choice_index = last_allowed;
             for (i, (weight, _)) in choices.iter().enumerate() {
+                if *weight <= 0.0 {
+                    continue;
+                }
                 value -= weight;
                 if value <= 0.0 {
                     choice_index = i;
@@ -448,13 +1615,15 @@ impl Dna {
         };
         if self.is_interactive() {
             let mut c = Vec::new();
-            for (weight, name) in choices {
-                if weight > 0.0 {
-                    c.push(name.into());
+            let mut indices = Vec::new();
+            for (i, (weight, name)) in choices.iter().enumerate() {
+                if *weight > 0.0 {
+                    c.push((*name).into());
+                    indices.push(i as u32);
                 }
             }
-            self.add_parameter(
-                ParameterKind::Categorical,
+            let parameter = self.add_parameter(
+                kind,
                 name.into(),
                 choices[choice_index].1.to_string(),
                 None,
@@ -464,9 +1633,22 @@ impl Dna {
                 hash,
                 c,
             );
+            parameter.ordinal = ordinal;
+            parameter.choice_indices = indices;
         }
         self.advance();
-        choice_index as u32
+        choice_index
+    }
+
+    /// Returns the index of a choice. Choices with zero weight are never selected;
+    /// at least one choice must have positive weight.
+    pub fn index<const T: usize>(&mut self, name: &str, choices: [(f32, &str); T]) -> u32 {
+        self.draw_index(name, &choices, ParameterKind::Categorical, false) as u32
+    }
+
+    /// Returns the index of a choice from a slice of choices.
+    pub fn index_slice(&mut self, name: &str, choices: &[(f32, &str)]) -> u32 {
+        self.draw_index(name, choices, ParameterKind::Categorical, false) as u32
     }
 
     /// Returns a choice.
@@ -475,44 +1657,108 @@ impl Dna {
         name: &str,
         choices: [(f32, &str, X); T],
     ) -> X {
+        let i = self.draw_index(
+            name,
+            &choices.each_ref().map(|(w, n, _)| (*w, *n)),
+            ParameterKind::Categorical,
+            false,
+        );
+        choices[i].2.clone()
+    }
+
+    /// Returns a choice from choices listed in order, so that neighboring choices are similar.
+    /// Mutation prefers neighboring choices.
+    pub fn ordered_choice<X: Clone, const T: usize>(
+        &mut self,
+        name: &str,
+        choices: [(f32, &str, X); T],
+    ) -> X {
+        let i = self.draw_index(
+            name,
+            &choices.each_ref().map(|(w, n, _)| (*w, *n)),
+            ParameterKind::Categorical,
+            true,
+        );
+        choices[i].2.clone()
+    }
+
+    /// Returns a boolean parameter that is true with the given probability.
+    pub fn bool(&mut self, name: &str, probability: f32) -> bool {
+        let choices = [(1.0 - probability, "false"), (probability, "true")];
+        self.draw_index(name, &choices, ParameterKind::Bool, false) == 1
+    }
+
+    /// Returns an enum parameter. Enums implement `DnaChoice`,
+    /// which can be derived with the `derive` feature.
+    pub fn enum_choice<X: DnaChoice>(&mut self, name: &str) -> X {
+        let i = self.draw_index(name, X::CHOICES, ParameterKind::Categorical, X::ORDINAL);
+        X::from_index(i)
+    }
+
+    /// Returns a vector parameter with components packed into a single gene.
+    fn draw_vector(
+        &mut self,
+        name: &str,
+        kind: ParameterKind,
+        minimum: Vec3,
+        maximum: Vec3,
+    ) -> Vec3 {
         let hash = self.get_parameter_hash(name);
-        let value = self.draw_value(hash);
-        let choice_index = if (value as usize) < choices.len() && choices[value as usize].0 > 0.0 {
-            value as usize
-        } else {
-            let total_weight: f32 = choices.iter().map(|(weight, _, _)| weight).sum();
-            let mut value = value as f32 / ((1u64 << 32) as f32) * total_weight;
-            let mut choice_index = 0;
-            for (i, (weight, _, _)) in choices.iter().enumerate() {
-                value -= weight;
-                if value <= 0.0 {
-                    choice_index = i;
-                    break;
+        if let Some(Pending::Vector(v)) = self.pending.remove(&hash) {
+            // Values agree if they are equal at the displayed precision.
+            let agrees = match self.genome.get(&hash) {
+                Some(raw) => {
+                    let components = unpack_vector(*raw);
+                    (0..3).all(|i| {
+                        let x = components[i] as f32 / VECTOR_MAXIMUM[i] as f32;
+                        format!("{:.3}", lerp(minimum[i], maximum[i], x)) == format!("{:.3}", v[i])
+                    })
                 }
-            }
-            choice_index
-        };
-        if self.is_interactive() {
-            let mut c = Vec::new();
-            for (weight, name, _) in &choices {
-                if *weight > 0.0 {
-                    c.push((*name).into());
+                None => false,
+            };
+            if !agrees {
+                if let Some(raw) = Self::raw_from_vec3(minimum, maximum, v) {
+                    self.genome.insert(hash, raw);
                 }
             }
-            self.add_parameter(
-                ParameterKind::Categorical,
+        }
+        let value = self.draw_value(hash);
+        let components = unpack_vector(value);
+        let mut v = Vec3::ZERO;
+        for i in 0..3 {
+            let x = components[i] as f32 / VECTOR_MAXIMUM[i] as f32;
+            v[i] = lerp(minimum[i], maximum[i], x);
+        }
+        if self.is_interactive() {
+            let parameter = self.add_parameter(
+                kind,
                 name.into(),
-                choices[choice_index].1.to_string(),
+                format!("({:.3}, {:.3}, {:.3})", v.x, v.y, v.z),
                 None,
                 self.address.clone(),
-                choices.len() as u32 - 1,
-                choice_index as u32,
+                0xffffffff,
+                value,
                 hash,
-                c,
+                Vec::new(),
             );
+            parameter.value_vec3 = Some(v);
+            parameter.range = Some((minimum, maximum));
         }
         self.advance();
-        choices[choice_index].2.clone()
+        v
+    }
+
+    /// Returns a vector parameter with components in minimum...maximum.
+    /// Components have a resolution of 11, 11 and 10 bits.
+    pub fn vec3_in(&mut self, name: &str, minimum: Vec3, maximum: Vec3) -> Vec3 {
+        self.draw_vector(name, ParameterKind::Vector, minimum, maximum)
+    }
+
+    /// Returns a color parameter as Okhsl hue, saturation and lightness in 0...1.
+    /// Components are adjusted and interpolated in the perceptual Okhsl space,
+    /// with hue wrapping around. Convert to sRGB with `okhsl_to_srgb`.
+    pub fn color(&mut self, name: &str) -> Vec3 {
+        self.draw_vector(name, ParameterKind::Color, Vec3::ZERO, Vec3::ONE)
     }
 
     /// Start a new group (branch) of parameters under the previously drawn parameter.
@@ -545,46 +1791,532 @@ impl Dna {
         name: &str,
         mut choices: [(f32, &str, Box<dyn FnMut(&mut Dna) -> X>); T],
     ) -> X {
-        let hash = self.get_parameter_hash(name);
-        let value = self.draw_value(hash);
-        let choice_index = if (value as usize) < choices.len() && choices[value as usize].0 > 0.0 {
-            value as usize
-        } else {
-            let total_weight: f32 = choices.iter().map(|(weight, _, _)| weight).sum();
-            let mut value = value as f32 / ((1u64 << 32) as f32) * total_weight;
-            let mut choice_index = 0;
-            for (i, (weight, _, _)) in choices.iter().enumerate() {
-                value -= weight;
-                if value <= 0.0 {
-                    choice_index = i;
-                    break;
+        let i = self.draw_index(
+            name,
+            &choices.each_ref().map(|(w, n, _)| (*w, *n)),
+            ParameterKind::Categorical,
+            false,
+        );
+        self.group();
+        let x = (choices[i].2)(self);
+        self.ungroup();
+        x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::map3gen::*;
+    use super::*;
+
+    #[test]
+    pub fn crossover() {
+        for seed in 0..20 {
+            let mut a = Dna::new(seed);
+            genmap3palette(20.0, TilingMode::All, &mut a);
+            let mut b = Dna::new(seed + 1000);
+            genmap3palette(20.0, TilingMode::All, &mut b);
+            for mode in [
+                CrossoverMode::Uniform,
+                CrossoverMode::Subtree,
+                CrossoverMode::SinglePoint,
+            ] {
+                // Crossing over a parent with itself reproduces the parent.
+                let mut c = Dna::crossover(&a, &a, seed, mode);
+                genmap3palette(20.0, TilingMode::All, &mut c);
+                assert_eq!(a.parameters(), c.parameters());
+                for (x, y) in a.parameter_vector().iter().zip(c.parameter_vector()) {
+                    assert_eq!(x.hash(), y.hash());
+                    assert_eq!(x.raw(), y.raw());
+                }
+                // Every inherited gene comes from one of the parents.
+                let c = Dna::crossover(&a, &b, seed, mode);
+                let raw = |dna: &Dna, hash: &u64| {
+                    dna.parameter_vector()
+                        .iter()
+                        .find(|x| x.hash() == *hash)
+                        .map(|x| x.raw())
+                };
+                for (hash, value) in c.genome.iter() {
+                    assert!(raw(&a, hash) == Some(*value) || raw(&b, hash) == Some(*value));
                 }
             }
-            choice_index
+        }
+    }
+
+    #[test]
+    pub fn text_format() {
+        let mut a = Dna::new(1);
+        genmap3palette(20.0, TilingMode::Z, &mut a);
+        let header = DnaHeader::new("genmap3palette", GENERATOR_VERSION, 20.0, TilingMode::Z);
+        let text = a.to_text(&header);
+        let (header2, b) = Dna::from_text(&text).unwrap();
+        assert_eq!(header, header2);
+        assert_eq!(a.genome, b.genome);
+        assert_eq!(text, b.to_text(&header2));
+
+        let (legacy, c) = Dna::from_text("TilingMode XY\n1 2\n3 4\n").unwrap();
+        assert_eq!(legacy.version, 0);
+        assert_eq!(legacy.tiling, TilingMode::XY);
+        assert_eq!(c.hash_scheme(), HashScheme::Legacy);
+        assert_eq!(c.genome.len(), 2);
+        // Legacy files can be saved with the returned header.
+        let (legacy2, c2) = Dna::from_text(&c.to_text(&legacy)).unwrap();
+        assert_eq!(legacy2.generator, "");
+        assert_eq!(legacy2.tiling, TilingMode::XY);
+        assert_eq!(c.genome, c2.genome);
+
+        assert!(matches!(
+            Dna::from_text("funutd dna 1\n1 2\n3\n"),
+            Err(DnaError::Gene(3))
+        ));
+        assert!(matches!(
+            Dna::from_text("funutd dna 1\nflavor sweet\n"),
+            Err(DnaError::Header(2))
+        ));
+        assert!(matches!(
+            Dna::from_text("funutd dna 1000\n"),
+            Err(DnaError::UnsupportedVersion(1000))
+        ));
+    }
+
+    #[test]
+    pub fn migrate() {
+        // Stable hashes must never change.
+        assert_eq!(
+            Dna::get_hash(HashScheme::Stable, &[0], "seed"),
+            17943575748334009919
+        );
+        for seed in 0..20 {
+            let mut a = Dna::new(seed);
+            a.set_hash_scheme(HashScheme::Legacy);
+            let texture_a = genmap3palette(20.0, TilingMode::All, &mut a);
+            let mut b = Dna::migrate(&a, |dna| genmap3palette(20.0, TilingMode::All, dna));
+            assert_eq!(b.hash_scheme(), HashScheme::Stable);
+            let texture_b = genmap3palette(20.0, TilingMode::All, &mut b);
+            assert_eq!(texture_a.get_code(), texture_b.get_code());
+        }
+    }
+
+    #[test]
+    pub fn readable_text() {
+        let mut a = Dna::new(2);
+        let texture_a = genmap3palette(20.0, TilingMode::All, &mut a);
+        let header = DnaHeader::new("genmap3palette", GENERATOR_VERSION, 20.0, TilingMode::All);
+        let text = a.to_readable_text(&header);
+        let (header2, mut b) = Dna::from_text(&text).unwrap();
+        assert_eq!(header, header2);
+        let texture_b = genmap3palette(20.0, TilingMode::All, &mut b);
+        assert_eq!(texture_a.get_code(), texture_b.get_code());
+        assert_eq!(text, b.to_readable_text(&header));
+
+        // Edited decoded values take precedence over raw values. The last choice is edited,
+        // so the structure drawn before it is unchanged.
+        let parameters = a.parameter_vector();
+        let ci = parameters
+            .iter()
+            .rposition(|p| p.choices().len() > 1)
+            .unwrap();
+        let midpoint = |p: &Parameter| match p.mapping() {
+            Some(Mapping::Unit) => Some(0.5),
+            Some(Mapping::Linear(x, y) | Mapping::Exponential(x, y) | Mapping::Power(x, y, _)) => {
+                Some((x + y) * 0.5)
+            }
+            _ => None,
         };
-        if self.is_interactive() {
-            let mut c = Vec::new();
-            for (weight, name, _) in &choices {
-                if *weight > 0.0 {
-                    c.push((*name).into());
+        let fi = parameters[..ci]
+            .iter()
+            .position(|p| p.kind() == ParameterKind::Ordered && midpoint(p).is_some())
+            .unwrap();
+        let (f, c) = (&parameters[fi], &parameters[ci]);
+        let x = format!("{:.3}", midpoint(f).unwrap());
+        let other = c.choices().iter().find(|x| **x != *c.value()).unwrap();
+        // Parameter lines follow the 5 header lines.
+        let mut lines: Vec<String> = text.lines().map(String::from).collect();
+        lines[5 + fi] = lines[5 + fi].replace(&format!("= {} ;", f.value()), &format!("= {} ;", x));
+        lines[5 + ci] =
+            lines[5 + ci].replace(&format!("= {:?} ;", c.value()), &format!("= {:?} ;", other));
+        let (_, mut d) = Dna::from_text(&lines.join("\n")).unwrap();
+        genmap3palette(20.0, TilingMode::All, &mut d);
+        assert_eq!(*d.parameter(fi).value(), x);
+        assert_eq!(d.parameter(ci).value(), other);
+
+        // Names with escaped characters round trip.
+        let name = "line\nbreak \"quoted\" \\ \t\u{1b}\u{301}";
+        let mut e = Dna::new(3);
+        let x = e.f32(name);
+        let (_, mut f) = Dna::from_text(&e.to_readable_text(&header)).unwrap();
+        assert_eq!(f.f32(name), x);
+    }
+
+    #[test]
+    pub fn targeted_mutation() {
+        for seed in 0..20 {
+            let mut a = Dna::new(seed);
+            genmap3palette(20.0, TilingMode::All, &mut a);
+            let locked = a.parameter(1).hash();
+            a.lock(locked);
+            let prefix = a.parameter(a.parameters() - 1).address()[..1].to_vec();
+            let mutation = Mutation::new(1.0).prefix(&prefix);
+            let b = Dna::mutate_with(&a, seed, &mutation);
+            assert!(b.is_locked(locked));
+            for parameter in a.parameter_vector() {
+                let kept = b.genome.get(&parameter.hash()) == Some(&parameter.raw());
+                if parameter.hash() == locked || !parameter.address().starts_with(&prefix) {
+                    assert!(kept);
+                }
+            }
+            let c = Dna::mutate_with(&a, seed, &Mutation::new(1.0).name("hue"));
+            for parameter in a.parameter_vector() {
+                if !parameter.name().contains("hue") {
+                    assert_eq!(c.genome.get(&parameter.hash()), Some(&parameter.raw()));
                 }
             }
-            self.add_parameter(
-                ParameterKind::Categorical,
-                name.into(),
-                choices[choice_index].1.to_string(),
-                None,
-                self.address.clone(),
-                choices.len() as u32 - 1,
-                choice_index as u32,
-                hash,
-                c,
-            );
         }
-        self.advance();
-        self.group();
-        let x = (choices[choice_index].2)(self);
-        self.ungroup();
-        x
+    }
+
+    #[test]
+    pub fn graded_mutation() {
+        for seed in 0..20 {
+            let mut a = Dna::new(seed);
+            genmap3palette(20.0, TilingMode::All, &mut a);
+            let b = Dna::mutate_with(&a, seed, &Mutation::new(1.0).sigma(0.01));
+            for parameter in a.parameter_vector() {
+                let raw = parameter.raw() as f64;
+                let mutated = *b.genome.get(&parameter.hash()).unwrap_or(&0) as f64;
+                if matches!(parameter.kind(), ParameterKind::Ordered) {
+                    assert!((mutated - raw).abs() <= 0.1 * parameter.maximum() as f64);
+                } else if parameter.is_ordinal() && parameter.maximum() < 50 {
+                    assert_eq!((mutated - raw).abs(), 1.0);
+                }
+            }
+            // Large steps that cannot be reflected still change the value.
+            let c = Dna::mutate_with(&a, seed, &Mutation::new(1.0).sigma(10.0));
+            for parameter in a.parameter_vector() {
+                if parameter.is_ordinal() && parameter.maximum() > 0 {
+                    assert_ne!(c.genome.get(&parameter.hash()), Some(&parameter.raw()));
+                }
+            }
+            // Without sigma, ordinal parameters are redrawn.
+            let d = Dna::mutate_with(&a, seed, &Mutation::new(1.0));
+            for parameter in a.parameter_vector() {
+                if parameter.is_ordinal() {
+                    assert!(!d.genome.contains_key(&parameter.hash()));
+                }
+            }
+        }
+        // Ordinal choices step over choices with zero weight.
+        let choices = [(1.0, "a", 0), (0.0, "b", 1), (1.0, "c", 2)];
+        for seed in 0..20 {
+            let mut a = Dna::new(seed);
+            a.ordered_choice("x", choices);
+            a.set_choice(a.parameter(0).hash(), "a").unwrap();
+            a.reset();
+            assert_eq!(a.ordered_choice("x", choices), 0);
+            let mut b = Dna::mutate_with(&a, seed, &Mutation::new(1.0).sigma(0.01));
+            assert_eq!(b.ordered_choice("x", choices), 2);
+        }
+    }
+
+    #[test]
+    pub fn interpolate() {
+        let mut a = Dna::new(3);
+        let texture_a = genmap3palette(20.0, TilingMode::All, &mut a);
+        let mut b = Dna::mutate_with(&a, 1, &Mutation::new(0.5).structural(false));
+        let texture_b = genmap3palette(20.0, TilingMode::All, &mut b);
+        let mut c = Dna::interpolate(&a, &b, 0.0);
+        assert_eq!(
+            genmap3palette(20.0, TilingMode::All, &mut c).get_code(),
+            texture_a.get_code()
+        );
+        let mut c = Dna::interpolate(&a, &b, 1.0);
+        assert_eq!(
+            genmap3palette(20.0, TilingMode::All, &mut c).get_code(),
+            texture_b.get_code()
+        );
+        let mut c = Dna::interpolate(&a, &b, 0.3);
+        genmap3palette(20.0, TilingMode::All, &mut c);
+        for ((x, y), z) in a
+            .parameter_vector()
+            .iter()
+            .zip(b.parameter_vector())
+            .zip(c.parameter_vector())
+        {
+            assert_eq!(x.hash(), z.hash());
+            match x.kind() {
+                ParameterKind::Vector => {
+                    let (xs, ys, zs) = (
+                        unpack_vector(x.raw()),
+                        unpack_vector(y.raw()),
+                        unpack_vector(z.raw()),
+                    );
+                    for i in 0..3 {
+                        assert!(min(xs[i], ys[i]) <= zs[i] && zs[i] <= max(xs[i], ys[i]));
+                    }
+                }
+                // Hue of colors may wrap around.
+                ParameterKind::Color => (),
+                _ => {
+                    let (low, high) = (min(x.raw(), y.raw()), max(x.raw(), y.raw()));
+                    assert!(low <= z.raw() && z.raw() <= high);
+                }
+            }
+        }
+    }
+
+    #[test]
+    pub fn set_by_value() {
+        let mut a = Dna::new(4);
+        genmap3palette(20.0, TilingMode::All, &mut a);
+        let parameters = a.parameter_vector().clone();
+        for parameter in &parameters {
+            if let (Some(mapping), Some(value)) = (parameter.mapping(), parameter.value_f32()) {
+                a.set_f32(parameter.hash(), value).unwrap();
+                let raw = a.genome[&parameter.hash()] as f64;
+                let scale = parameter.maximum() as f64;
+                assert!((raw - parameter.raw() as f64).abs() <= 1.0e-6 * scale + 1.0);
+                if mapping != Mapping::Custom {
+                    assert!(matches!(
+                        a.set_f32(parameter.hash(), 1.0e9),
+                        Err(DnaError::OutOfRange(_))
+                    ));
+                }
+            }
+            for choice in parameter.choices() {
+                a.set_choice(parameter.hash(), choice).unwrap();
+            }
+            if !parameter.choices().is_empty() {
+                assert!(matches!(
+                    a.set_choice(parameter.hash(), "no such choice"),
+                    Err(DnaError::UnknownChoice(_))
+                ));
+            }
+        }
+        assert!(matches!(
+            a.set_f32(0, 0.0),
+            Err(DnaError::UnknownParameter(0))
+        ));
+
+        // Values set by `set_f32` are drawn back.
+        let draws: [fn(&mut Dna) -> f32; 5] = [
+            |dna| dna.f32("x"),
+            |dna| dna.f32_in("x", -2.0, 3.0),
+            |dna| dna.f32_xerp("x", 0.5, 8.0),
+            |dna| dna.f32_pow("x", 1.0, 2.0, 2.0),
+            |dna| dna.u32_in("x", 3, 9) as f32,
+        ];
+        for draw in draws {
+            let mut e = Dna::new(7);
+            draw(&mut e);
+            let (hash, mapping) = (e.parameter(0).hash(), e.parameter(0).mapping().unwrap());
+            for x in [0.0, 0.5, 1.0] {
+                let value = mapping.forward(x).unwrap();
+                e.set_f32(hash, value).unwrap();
+                e.reset();
+                assert!((draw(&mut e) - value).abs() <= 1.0e-6 * value.abs().max(1.0));
+            }
+        }
+
+        // Readable values without raw values are resolved during generation.
+        let mut b = Dna::new(5);
+        let texture_b = genmap3palette(20.0, TilingMode::All, &mut b);
+        let header = DnaHeader::new("genmap3palette", GENERATOR_VERSION, 20.0, TilingMode::All);
+        let mut text = String::new();
+        for line in b.to_readable_text(&header).lines() {
+            let line = match line.rsplit_once(" ; raw ") {
+                Some((line, _)) if line.contains("\"basis\"") => line,
+                _ => line,
+            };
+            text.push_str(line);
+            text.push('\n');
+        }
+        let (_, mut c) = Dna::from_text(&text).unwrap();
+        assert!(c.unresolved() > 0);
+        let texture_c = genmap3palette(20.0, TilingMode::All, &mut c);
+        assert_eq!(c.unresolved(), 0);
+        assert_eq!(texture_b.get_code(), texture_c.get_code());
+
+        // Choices with zero weight are never selected.
+        let choices = [(0.0, "a"), (1.0, "b"), (0.0, "c"), (0.5, "d"), (0.0, "e")];
+        let mut d = Dna::new(6);
+        d.index("choice", choices);
+        let hash = d.parameter(0).hash();
+        for raw in [0, 1, 2, 3, 4, 5, 1 << 31, u32::MAX] {
+            d.reset();
+            d.set_value(hash, raw);
+            assert!(matches!(d.index("choice", choices), 1 | 3));
+        }
+    }
+
+    #[test]
+    pub fn prune_and_diff() {
+        let mut a = Dna::new(8);
+        genmap3palette(20.0, TilingMode::All, &mut a);
+        assert!(Dna::diff(&a, &a).is_empty());
+        let mut b = Dna::mutate(&a, 9, 0.5);
+        genmap3palette(20.0, TilingMode::All, &mut b);
+        let diff = Dna::diff(&a, &b);
+        assert!(!diff.is_empty());
+        let drawn = |dna: &Dna, address: &Vec<u32>, name: &String| {
+            dna.parameter_vector()
+                .iter()
+                .any(|x| x.address() == address && x.name() == name)
+        };
+        for change in &diff {
+            match change {
+                ParameterDiff::Changed { address, name, .. } => {
+                    assert!(drawn(&a, address, name) && drawn(&b, address, name));
+                }
+                ParameterDiff::Added { address, name, .. } => {
+                    assert!(!drawn(&a, address, name) && drawn(&b, address, name));
+                }
+                ParameterDiff::Removed { address, name, .. } => {
+                    assert!(drawn(&a, address, name) && !drawn(&b, address, name));
+                }
+            }
+        }
+
+        // Offspring inherit the genes of both parents, not all of which are drawn.
+        let mut c = Dna::crossover(&a, &b, 10, CrossoverMode::Uniform);
+        let texture_c = genmap3palette(20.0, TilingMode::All, &mut c);
+        c.prune();
+        assert_eq!(c.genome.len(), c.parameters());
+        // Nothing is drawn yet after a reset, so pruning keeps the genome.
+        let genes = c.genome.len();
+        c.reset();
+        c.prune();
+        assert_eq!(c.genome.len(), genes);
+        c.reset();
+        assert_eq!(
+            genmap3palette(20.0, TilingMode::All, &mut c).get_code(),
+            texture_c.get_code()
+        );
+    }
+
+    #[test]
+    pub fn collisions() {
+        fn nest(dna: &mut Dna, depth: usize) -> f32 {
+            if depth == 0 {
+                dna.f32("x")
+            } else {
+                dna.generate(|dna| nest(dna, depth - 1))
+            }
+        }
+        // Addresses of the two draws differ only in the first of 10 levels.
+        let mut dna = Dna::new(0);
+        dna.set_collision_check(true);
+        let (x, y) = (nest(&mut dna, 9), nest(&mut dna, 9));
+        assert_eq!(x, y);
+        assert_eq!(dna.collisions().len(), 1);
+        let collision = &dna.collisions()[0];
+        assert_eq!(collision.first_address[0], 0);
+        assert_eq!(collision.second_address[0], 1);
+        assert_eq!(collision.first_address[1..], collision.second_address[1..]);
+        // The shared gene is written once in readable text.
+        let header = DnaHeader::new("test", 0, 0.0, TilingMode::All);
+        let (_, mut b) = Dna::from_text(&dna.to_readable_text(&header)).unwrap();
+        assert_eq!(nest(&mut b, 9), x);
+        dna.reset();
+        assert!(dna.collisions().is_empty());
+
+        let mut dna = Dna::migrate_to(&dna, HashScheme::FullPath, |dna| {
+            (nest(dna, 9), nest(dna, 9))
+        });
+        dna.set_collision_check(true);
+        nest(&mut dna, 9);
+        nest(&mut dna, 9);
+        assert!(dna.collisions().is_empty());
+        let (_, b) = Dna::from_text(&dna.to_text(&header)).unwrap();
+        assert_eq!(b.hash_scheme(), HashScheme::FullPath);
+    }
+
+    #[test]
+    pub fn typed_parameters() {
+        let minimum = vec3(-1.0, 0.0, 2.0);
+        let maximum = vec3(1.0, 0.5, 4.0);
+        let draw = |dna: &mut Dna| {
+            (
+                dna.bool("flag", 0.5),
+                dna.vec3_in("vector", minimum, maximum),
+                dna.color("color"),
+            )
+        };
+        let mut a = Dna::new(6);
+        let (_, v, c) = draw(&mut a);
+        assert!(v.cmpge(minimum).all() && v.cmple(maximum).all());
+        assert!(c.cmpge(Vec3::ZERO).all() && c.cmple(Vec3::ONE).all());
+        let kinds: Vec<ParameterKind> = a.parameter_vector().iter().map(|x| x.kind()).collect();
+        assert_eq!(
+            kinds,
+            [
+                ParameterKind::Bool,
+                ParameterKind::Vector,
+                ParameterKind::Color
+            ]
+        );
+        assert_eq!(a.parameter(1).value_vec3(), Some(v));
+        assert_eq!(a.parameter(1).range(), Some((minimum, maximum)));
+
+        let target = vec3(0.5, 0.25, 3.0);
+        a.set_choice(a.parameter(0).hash(), "true").unwrap();
+        a.set_vec3(a.parameter(1).hash(), target).unwrap();
+        assert!(matches!(
+            a.set_vec3(a.parameter(2).hash(), vec3(0.5, 0.5, 2.0)),
+            Err(DnaError::OutOfRange(_))
+        ));
+        a.reset();
+        let (flag, v, _) = draw(&mut a);
+        assert!(flag);
+        assert!((v - target).abs().max_element() < 1.0e-2);
+
+        for seed in 0..20 {
+            let mut b = Dna::mutate_with(&a, seed, &Mutation::new(1.0));
+            let (_, v, c) = draw(&mut b);
+            assert!(v.cmpge(minimum).all() && v.cmple(maximum).all());
+            assert!(c.cmpge(Vec3::ZERO).all() && c.cmple(Vec3::ONE).all());
+        }
+
+        // Vector values can be read back without raw values.
+        let header = DnaHeader::new("test", 0, 0.0, TilingMode::All);
+        let text: String = a
+            .to_readable_text(&header)
+            .lines()
+            .map(|line| match line.split_once(" ; raw ") {
+                Some((line, _)) => format!("{}\n", line),
+                None => format!("{}\n", line),
+            })
+            .collect();
+        let (_, mut b) = Dna::from_text(&text).unwrap();
+        assert_eq!(b.unresolved(), 3);
+        let (flag, v, _) = draw(&mut b);
+        assert_eq!(b.unresolved(), 0);
+        assert!(flag);
+        assert!((v - target).abs().max_element() < 1.0e-2);
+    }
+
+    #[cfg(feature = "derive")]
+    #[test]
+    pub fn derive_choice() {
+        #[derive(DnaChoice, Debug, PartialEq)]
+        #[dna(ordinal)]
+        enum Size {
+            Small,
+            #[dna(weight = 2)]
+            Medium,
+            #[dna(name = "extra large", weight = 0.0)]
+            Large,
+        }
+        assert_eq!(
+            Size::CHOICES,
+            &[(1.0, "Small"), (2.0, "Medium"), (0.0, "extra large")]
+        );
+        let mut a = Dna::new(7);
+        let size: Size = a.enum_choice("size");
+        assert_ne!(size, Size::Large);
+        assert!(a.parameter(0).is_ordinal());
+        assert_eq!(a.parameter(0).choices().len(), 2);
+        a.set_choice(a.parameter(0).hash(), "Small").unwrap();
+        a.reset();
+        assert_eq!(a.enum_choice::<Size>("size"), Size::Small);
     }
 }
@@ -0,0 +1,545 @@
+//! Evolution of textures toward a fitness target.
+
+use super::dna::*;
+use super::map3base::*;
+use super::math::*;
+use super::*;
+extern crate alloc;
+use alloc::{boxed::Box, vec::Vec};
+
+/// Square grid of texture values sampled from the plane z = 0.5 over 0...1 in x and y,
+/// which is the area shown in the editor.
+#[derive(Clone)]
+pub struct Samples {
+    size: usize,
+    values: Vec<Vec3a>,
+}
+
+impl Samples {
+    /// Sample a texture at `size` x `size` pixel centers.
+    pub fn new(texture: &dyn Texture, size: usize) -> Self {
+        assert!(size > 0);
+        let mut values = Vec::with_capacity(size * size);
+        for y in 0..size {
+            for x in 0..size {
+                let point = vec3a(
+                    (x as f32 + 0.5) / size as f32,
+                    (y as f32 + 0.5) / size as f32,
+                    0.5,
+                );
+                values.push(texture.at(point));
+            }
+        }
+        Self { size, values }
+    }
+
+    /// Samples from values in row-major order, for example, from a reference image.
+    /// Values should be in the canonical texture range -1...1.
+    pub fn from_values(size: usize, values: Vec<Vec3a>) -> Self {
+        assert!(size > 0 && values.len() == size * size);
+        Self { size, values }
+    }
+
+    /// Width and height of the grid.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Sampled values in row-major order.
+    pub fn values(&self) -> &Vec<Vec3a> {
+        &self.values
+    }
+
+    /// Value at pixel (x, y).
+    pub fn at(&self, x: usize, y: usize) -> Vec3a {
+        self.values[y * self.size + x]
+    }
+
+    /// Mean value.
+    pub fn mean(&self) -> Vec3a {
+        self.values.iter().fold(Vec3a::zero(), |acc, &v| acc + v) / self.values.len() as f32
+    }
+}
+
+/// Per channel histograms of sample values in -1...1 with `bins` bins per channel.
+/// Each histogram sums to 1. Values outside the range go to the end bins.
+pub fn histogram(samples: &Samples, bins: usize) -> Vec<f32> {
+    assert!(bins > 0);
+    let mut histogram = vec![0.0; bins * 3];
+    let weight = 1.0 / samples.values().len() as f32;
+    for v in samples.values() {
+        for (channel, x) in [v.x, v.y, v.z].into_iter().enumerate() {
+            let bin = clamp(0.0, (bins - 1) as f32, floor((x + 1.0) * 0.5 * bins as f32));
+            histogram[channel * bins + bin as usize] += weight;
+        }
+    }
+    histogram
+}
+
+/// Radially averaged log power spectrum of sample luminance. Element `i` is the band
+/// at `i + 1` cycles per unit, up to the Nyquist frequency.
+pub fn spectrum(samples: &Samples) -> Vec<f32> {
+    let n = samples.size();
+    let luminance: Vec<f32> = samples
+        .values()
+        .iter()
+        .map(|v| 0.2126 * v.x + 0.7152 * v.y + 0.0722 * v.z)
+        .collect();
+    let (cosine, sine): (Vec<f32>, Vec<f32>) = (0..n)
+        .map(|i| {
+            let phase = f32::TAU * i as f32 / n as f32;
+            (cos(phase), sin(phase))
+        })
+        .unzip();
+    // Separable discrete Fourier transform: rows first, then columns.
+    let mut rows = vec![(0.0, 0.0); n * n];
+    for y in 0..n {
+        for u in 0..n {
+            let (mut re, mut im) = (0.0, 0.0);
+            for x in 0..n {
+                let k = (u * x) % n;
+                re += luminance[y * n + x] * cosine[k];
+                im -= luminance[y * n + x] * sine[k];
+            }
+            rows[y * n + u] = (re, im);
+        }
+    }
+    let bands = max(1, n / 2);
+    let mut power = vec![0.0; bands];
+    let mut count = vec![0usize; bands];
+    for u in 0..n {
+        for v in 0..n {
+            let (mut re, mut im) = (0.0, 0.0);
+            for y in 0..n {
+                let k = (v * y) % n;
+                let (row_re, row_im) = rows[y * n + u];
+                re += row_re * cosine[k] + row_im * sine[k];
+                im += row_im * cosine[k] - row_re * sine[k];
+            }
+            // Signed frequencies.
+            let fu = min(u, n - u) as f32;
+            let fv = min(v, n - v) as f32;
+            let band = round(sqrt(fu * fu + fv * fv)) as usize;
+            if band >= 1 && band <= bands {
+                power[band - 1] += (re * re + im * im) / squared((n * n) as f32);
+                count[band - 1] += 1;
+            }
+        }
+    }
+    power
+        .iter()
+        .zip(count.iter())
+        .map(|(&p, &c)| log(1.0e-9 + p / max(1, c) as f32))
+        .collect()
+}
+
+/// Fitness measures how well a texture meets a target. Higher is better.
+/// Closures taking the texture and its samples implement `Fitness`.
+pub trait Fitness {
+    /// Fitness of `texture`, whose values sampled with `Samples::new` are in `samples`.
+    fn fitness(&self, texture: &dyn Texture, samples: &Samples) -> f32;
+}
+
+impl<F: Fn(&dyn Texture, &Samples) -> f32> Fitness for F {
+    fn fitness(&self, texture: &dyn Texture, samples: &Samples) -> f32 {
+        self(texture, samples)
+    }
+}
+
+/// Target mean value. Fitness is the negative distance to the target.
+#[derive(Clone)]
+pub struct MeanColor {
+    target: Vec3a,
+}
+
+impl MeanColor {
+    /// Target mean value in the canonical texture range -1...1.
+    pub fn new(target: Vec3) -> Self {
+        Self {
+            target: target.into(),
+        }
+    }
+}
+
+impl Fitness for MeanColor {
+    fn fitness(&self, _texture: &dyn Texture, samples: &Samples) -> f32 {
+        -(samples.mean() - self.target).length()
+    }
+}
+
+/// Target value distribution. Fitness is the negative L1 distance between histograms,
+/// averaged over channels.
+#[derive(Clone)]
+pub struct HistogramMatch {
+    bins: usize,
+    target: Vec<f32>,
+}
+
+impl HistogramMatch {
+    /// Match the value distribution of a reference with `bins` bins per channel.
+    pub fn new(reference: &Samples, bins: usize) -> Self {
+        Self {
+            bins,
+            target: histogram(reference, bins),
+        }
+    }
+}
+
+impl Fitness for HistogramMatch {
+    fn fitness(&self, _texture: &dyn Texture, samples: &Samples) -> f32 {
+        let histogram = histogram(samples, self.bins);
+        -histogram
+            .iter()
+            .zip(self.target.iter())
+            .map(|(x, y)| abs(x - y))
+            .sum::<f32>()
+            / 3.0
+    }
+}
+
+/// Target frequency content. Fitness is the negative mean squared difference
+/// of log power spectra. Samples must be the same size as the reference.
+#[derive(Clone)]
+pub struct SpectrumMatch {
+    target: Vec<f32>,
+}
+
+impl SpectrumMatch {
+    /// Match the spectrum of a reference.
+    pub fn new(reference: &Samples) -> Self {
+        Self {
+            target: spectrum(reference),
+        }
+    }
+}
+
+impl Fitness for SpectrumMatch {
+    fn fitness(&self, _texture: &dyn Texture, samples: &Samples) -> f32 {
+        let spectrum = spectrum(samples);
+        assert_eq!(spectrum.len(), self.target.len());
+        -spectrum
+            .iter()
+            .zip(self.target.iter())
+            .map(|(x, y)| squared(x - y))
+            .sum::<f32>()
+            / spectrum.len() as f32
+    }
+}
+
+/// Similarity to a reference image. Fitness is the negative mean squared difference
+/// per pixel. Samples must be the same size as the reference.
+#[derive(Clone)]
+pub struct ReferenceImage {
+    reference: Samples,
+}
+
+impl ReferenceImage {
+    pub fn new(reference: Samples) -> Self {
+        Self { reference }
+    }
+}
+
+impl Fitness for ReferenceImage {
+    fn fitness(&self, _texture: &dyn Texture, samples: &Samples) -> f32 {
+        assert_eq!(samples.size(), self.reference.size());
+        -samples
+            .values()
+            .iter()
+            .zip(self.reference.values().iter())
+            .map(|(x, y)| (*x - *y).length_squared())
+            .sum::<f32>()
+            / samples.values().len() as f32
+    }
+}
+
+/// Tileability in the xy plane. Fitness is the negative mean distance between values
+/// on opposite edges of the unit square. Textures generated with `tile_xy` or `tile_all`
+/// score 0, the maximum.
+#[derive(Clone)]
+pub struct Tileability {
+    samples: usize,
+}
+
+impl Tileability {
+    /// Compare `samples` points along each pair of edges.
+    pub fn new(samples: usize) -> Self {
+        assert!(samples > 0);
+        Self { samples }
+    }
+}
+
+impl Fitness for Tileability {
+    fn fitness(&self, texture: &dyn Texture, _samples: &Samples) -> f32 {
+        let mut distance = 0.0;
+        for i in 0..self.samples {
+            let t = (i as f32 + 0.5) / self.samples as f32;
+            distance += (texture.at(vec3a(0.0, t, 0.5)) - texture.at(vec3a(1.0, t, 0.5))).length();
+            distance += (texture.at(vec3a(t, 0.0, 0.5)) - texture.at(vec3a(t, 1.0, 0.5))).length();
+        }
+        -distance / (2 * self.samples) as f32
+    }
+}
+
+/// Weighted sum of fitness functions.
+pub struct Weighted {
+    terms: Vec<(f32, Box<dyn Fitness>)>,
+}
+
+impl Weighted {
+    /// Create an empty sum.
+    pub fn new() -> Self {
+        Self { terms: Vec::new() }
+    }
+
+    /// Add a term with the given weight.
+    pub fn add<F: 'static + Fitness>(mut self, weight: f32, fitness: F) -> Self {
+        self.terms.push((weight, Box::new(fitness)));
+        self
+    }
+}
+
+impl Default for Weighted {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Fitness for Weighted {
+    fn fitness(&self, texture: &dyn Texture, samples: &Samples) -> f32 {
+        self.terms
+            .iter()
+            .map(|(weight, term)| weight * term.fitness(texture, samples))
+            .sum()
+    }
+}
+
+/// Member of an evolving population.
+#[derive(Clone)]
+pub struct Individual {
+    /// Dna of the individual. Parameters of the latest generation pass are recorded.
+    pub dna: Dna,
+    /// Fitness of the texture generated from the Dna.
+    pub fitness: f32,
+}
+
+/// Settings for a genetic optimizer over `Dna`.
+/// Each generation, the fittest individuals are kept as is and the rest of the population
+/// is replaced with offspring of parents chosen in tournaments.
+/// Runs are deterministic for a given seed.
+#[derive(Clone)]
+pub struct Evolution {
+    seed: u64,
+    population: usize,
+    generations: usize,
+    elite: usize,
+    tournament: usize,
+    crossover: Option<(f32, CrossoverMode)>,
+    mutation: Mutation,
+    sample_size: usize,
+}
+
+impl Evolution {
+    /// Default settings: population 32, 20 generations, 2 elite individuals,
+    /// tournaments of 3, subtree crossover with probability 0.5, mutation probability 0.1
+    /// and 32 x 32 samples.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            population: 32,
+            generations: 20,
+            elite: 2,
+            tournament: 3,
+            crossover: Some((0.5, CrossoverMode::Subtree)),
+            mutation: Mutation::new(0.1),
+            sample_size: 32,
+        }
+    }
+
+    /// Population size (population > 0).
+    pub fn population(mut self, population: usize) -> Self {
+        assert!(population > 0);
+        self.population = population;
+        self
+    }
+
+    /// Number of generations after the initial population.
+    pub fn generations(mut self, generations: usize) -> Self {
+        self.generations = generations;
+        self
+    }
+
+    /// Number of fittest individuals carried over to the next generation unchanged.
+    pub fn elite(mut self, elite: usize) -> Self {
+        self.elite = elite;
+        self
+    }
+
+    /// Number of individuals competing for parenthood (tournament > 0).
+    pub fn tournament(mut self, tournament: usize) -> Self {
+        assert!(tournament > 0);
+        self.tournament = tournament;
+        self
+    }
+
+    /// Probability and mode of crossover. Offspring that are not crossed over
+    /// are copies of a single parent. Mutation is applied in either case.
+    pub fn crossover(mut self, probability: f32, mode: CrossoverMode) -> Self {
+        self.crossover = Some((probability, mode));
+        self
+    }
+
+    /// Disable crossover.
+    pub fn no_crossover(mut self) -> Self {
+        self.crossover = None;
+        self
+    }
+
+    /// Mutation settings for offspring.
+    pub fn mutation(mut self, mutation: Mutation) -> Self {
+        self.mutation = mutation;
+        self
+    }
+
+    /// Width and height of the sample grid passed to fitness functions.
+    pub fn sample_size(mut self, size: usize) -> Self {
+        assert!(size > 0);
+        self.sample_size = size;
+        self
+    }
+
+    /// Generate a texture from the Dna and evaluate it.
+    fn evaluate<G, F>(&self, mut dna: Dna, generator: &mut G, fitness: &F) -> Individual
+    where
+        G: FnMut(&mut Dna) -> Box<dyn Texture>,
+        F: Fitness + ?Sized,
+    {
+        dna.set_interactive(true);
+        dna.reset();
+        let texture = generator(&mut dna);
+        let samples = Samples::new(&*texture, self.sample_size);
+        let mut fitness = fitness.fitness(&*texture, &samples);
+        // Rank failed evaluations last.
+        if fitness.is_nan() {
+            fitness = f32::NEG_INFINITY;
+        }
+        Individual { dna, fitness }
+    }
+
+    /// Choose the fittest of random individuals. The population is sorted, fittest first.
+    fn select<'a>(&self, rnd: &mut Rnd, population: &'a [Individual]) -> &'a Individual {
+        let mut best = population.len() - 1;
+        for _ in 0..self.tournament {
+            best = min(best, rnd.u64_to(population.len() as u64) as usize);
+        }
+        &population[best]
+    }
+
+    /// Run the optimizer. `generator` generates a texture from Dna, for example,
+    /// `|dna| genmap3palette(20.0, TilingMode::All, dna)`.
+    /// After each generation, including the initial one, `progress` is called with
+    /// the generation number and the population sorted fittest first;
+    /// return false to stop early. Returns the fittest individual found.
+    pub fn run<G, F, P>(&self, mut generator: G, fitness: &F, mut progress: P) -> Individual
+    where
+        G: FnMut(&mut Dna) -> Box<dyn Texture>,
+        F: Fitness + ?Sized,
+        P: FnMut(usize, &[Individual]) -> bool,
+    {
+        let mut rnd = Rnd::from_u64(self.seed);
+        let sort = |population: &mut Vec<Individual>| {
+            population.sort_by(|a, b| b.fitness.total_cmp(&a.fitness));
+        };
+        let mut population: Vec<Individual> = (0..self.population)
+            .map(|_| self.evaluate(Dna::new(rnd.u64()), &mut generator, fitness))
+            .collect();
+        sort(&mut population);
+        if !progress(0, &population) {
+            return population.swap_remove(0);
+        }
+        for generation in 1..=self.generations {
+            let mut next: Vec<Individual> = population
+                .iter()
+                .take(min(self.elite, self.population))
+                .cloned()
+                .collect();
+            while next.len() < self.population {
+                let a = self.select(&mut rnd, &population);
+                let child = match self.crossover {
+                    Some((probability, mode)) if rnd.f32() < probability => {
+                        let b = self.select(&mut rnd, &population);
+                        Dna::crossover(&a.dna, &b.dna, rnd.u64(), mode)
+                    }
+                    _ => a.dna.clone(),
+                };
+                let child = Dna::mutate_with(&child, rnd.u64(), &self.mutation);
+                next.push(self.evaluate(child, &mut generator, fitness));
+            }
+            population = next;
+            sort(&mut population);
+            if !progress(generation, &population) {
+                break;
+            }
+        }
+        population.swap_remove(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::map3gen::*;
+    use super::*;
+
+    #[test]
+    pub fn evolve() {
+        let generator = |dna: &mut Dna| genmap3palette(10.0, TilingMode::All, dna);
+        let target = MeanColor::new(vec3(0.5, -0.5, 0.0));
+        let evolution = Evolution::new(1)
+            .population(8)
+            .generations(4)
+            .sample_size(8);
+        let mut best = Vec::new();
+        let result = evolution.run(generator, &target, |_, population| {
+            best.push(population[0].fitness);
+            true
+        });
+        assert_eq!(best.len(), 5);
+        // Elitism keeps the best fitness from decreasing.
+        assert!(best.windows(2).all(|x| x[0] <= x[1]));
+        assert_eq!(result.fitness, best[4]);
+        // Runs are deterministic.
+        let again = evolution.run(generator, &target, |_, _| true);
+        assert_eq!(again.fitness, result.fitness);
+        // Fitness is reproduced from the Dna.
+        let mut dna = result.dna.clone();
+        dna.reset();
+        let texture = generator(&mut dna);
+        let samples = Samples::new(&*texture, 8);
+        assert_eq!(target.fitness(&*texture, &samples), result.fitness);
+    }
+
+    #[test]
+    pub fn fitness() {
+        let tiled = genmap3palette(10.0, TilingMode::All, &mut Dna::new(2));
+        let untiled = genmap3palette(10.0, TilingMode::None, &mut Dna::new(2));
+        let samples = Samples::new(&*tiled, 16);
+        let tileability = Tileability::new(16);
+        assert!(abs(tileability.fitness(&*tiled, &samples)) < 1.0e-3);
+        let untiled_samples = Samples::new(&*untiled, 16);
+        assert!(tileability.fitness(&*untiled, &untiled_samples) < -1.0e-3);
+        // A reference matches itself perfectly.
+        assert_eq!(
+            HistogramMatch::new(&samples, 8).fitness(&*tiled, &samples),
+            0.0
+        );
+        assert_eq!(SpectrumMatch::new(&samples).fitness(&*tiled, &samples), 0.0);
+        assert_eq!(
+            ReferenceImage::new(samples.clone()).fitness(&*tiled, &samples),
+            0.0
+        );
+        let weighted = Weighted::new()
+            .add(1.0, Tileability::new(16))
+            .add(0.5, |_: &dyn Texture, samples: &Samples| samples.mean().x);
+        let expected = 0.5 * samples.mean().x + tileability.fitness(&*tiled, &samples);
+        assert!(abs(weighted.fitness(&*tiled, &samples) - expected) < 1.0e-6);
+    }
+}
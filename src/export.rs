@@ -0,0 +1,259 @@
+//! Saving textures to image files. Requires the `png` or `exr` feature.
+
+use super::map3base::*;
+use super::render::*;
+use super::*;
+extern crate alloc;
+use alloc::vec::Vec;
+
+/// Errors from saving images.
+#[derive(Debug)]
+pub enum SaveError {
+    /// The file could not be written.
+    Io(std::io::Error),
+    /// The PNG encoder failed.
+    #[cfg(feature = "png")]
+    Png(png::EncodingError),
+    /// The OpenEXR encoder failed.
+    #[cfg(feature = "exr")]
+    Exr(exr::error::Error),
+}
+
+impl core::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            SaveError::Io(error) => write!(f, "I/O error: {}", error),
+            #[cfg(feature = "png")]
+            SaveError::Png(error) => write!(f, "PNG error: {}", error),
+            #[cfg(feature = "exr")]
+            SaveError::Exr(error) => write!(f, "OpenEXR error: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SaveError::Io(error) => Some(error),
+            #[cfg(feature = "png")]
+            SaveError::Png(error) => Some(error),
+            #[cfg(feature = "exr")]
+            SaveError::Exr(error) => Some(error),
+        }
+    }
+}
+
+/// Bit depth of saved PNG images.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BitDepth {
+    Eight,
+    Sixteen,
+}
+
+/// Options for saving textures to image files.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SaveOptions {
+    width: usize,
+    height: usize,
+    region: Region,
+    z: f32,
+    bit_depth: BitDepth,
+    color_space: Option<ColorSpace>,
+    samples: usize,
+}
+
+impl SaveOptions {
+    /// Save `width` x `height` pixels (width, height > 0) of the unit square at z = 0.5.
+    /// By default, values are colors, saved as 8-bit sRGB in PNG images and as linear
+    /// in OpenEXR images, and are not supersampled.
+    pub fn new(width: usize, height: usize) -> Self {
+        assert!(width > 0 && height > 0);
+        Self {
+            width,
+            height,
+            region: Region::unit(),
+            z: 0.5,
+            bit_depth: BitDepth::Eight,
+            color_space: None,
+            samples: 1,
+        }
+    }
+
+    /// Set the region of the texture slice to save.
+    pub fn region(mut self, region: Region) -> Self {
+        self.region = region;
+        self
+    }
+
+    /// Set depth of the texture slice to save.
+    pub fn z(mut self, z: f32) -> Self {
+        self.z = z;
+        self
+    }
+
+    /// Set bit depth of PNG images. OpenEXR images are always saved in 32-bit float.
+    pub fn bit_depth(mut self, bit_depth: BitDepth) -> Self {
+        self.bit_depth = bit_depth;
+        self
+    }
+
+    /// Set interpretation of texture values. Use `ColorSpace::Data` for height maps and normals.
+    /// The default is `ColorSpace::Srgb` for PNG images and `ColorSpace::Linear`
+    /// for OpenEXR images, where colors are conventionally linear.
+    pub fn color_space(mut self, color_space: ColorSpace) -> Self {
+        self.color_space = Some(color_space);
+        self
+    }
+
+    /// Supersample each pixel on a grid of `samples` x `samples` points (samples >= 1).
+    pub fn supersample(mut self, samples: usize) -> Self {
+        assert!(samples >= 1);
+        self.samples = samples;
+        self
+    }
+
+    /// Render pixel values of the texture in the color space.
+    fn render(&self, texture: &dyn Texture, color_space: ColorSpace) -> Vec<Vec3a> {
+        // The pixel format does not affect pixel values.
+        let format = RenderFormat::new(PixelFormat::Rgb32F)
+            .color_space(color_space)
+            .supersample(self.samples);
+        render_values(
+            texture,
+            self.width,
+            self.height,
+            self.region,
+            self.z,
+            &format,
+        )
+    }
+}
+
+/// Save texture as a PNG image. sRGB colors are tagged as sRGB and linear colors
+/// with a gamma of 1; data is not tagged.
+#[cfg(feature = "png")]
+pub fn save_png(
+    texture: &dyn Texture,
+    path: &std::path::Path,
+    options: &SaveOptions,
+) -> Result<(), SaveError> {
+    let file = std::fs::File::create(path).map_err(SaveError::Io)?;
+    write_png(texture, &mut std::io::BufWriter::new(file), options)
+}
+
+/// Write texture as a PNG image to the writer. See `save_png`.
+#[cfg(feature = "png")]
+pub fn write_png<W: std::io::Write>(
+    texture: &dyn Texture,
+    writer: W,
+    options: &SaveOptions,
+) -> Result<(), SaveError> {
+    let color_space = options.color_space.unwrap_or(ColorSpace::Srgb);
+    let values = options.render(texture, color_space);
+    let (depth, data) = match options.bit_depth {
+        BitDepth::Eight => (png::BitDepth::Eight, pixels_u8(&values, false, color_space)),
+        // 16-bit samples are big-endian.
+        BitDepth::Sixteen => (
+            png::BitDepth::Sixteen,
+            pixels_u16(&values, color_space)
+                .iter()
+                .flat_map(|x| x.to_be_bytes())
+                .collect(),
+        ),
+    };
+    let mut encoder = png::Encoder::new(writer, options.width as u32, options.height as u32);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(depth);
+    match color_space {
+        ColorSpace::Srgb => encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual),
+        ColorSpace::Linear => encoder.set_source_gamma(png::ScaledFloat::new(1.0)),
+        ColorSpace::Data => (),
+    }
+    let mut writer = encoder.write_header().map_err(SaveError::Png)?;
+    writer.write_image_data(&data).map_err(SaveError::Png)?;
+    writer.finish().map_err(SaveError::Png)
+}
+
+/// Save texture as an OpenEXR image with 32-bit float RGB channels.
+/// Colors are saved linear by default. With `ColorSpace::Data`, texture values are saved
+/// without mapping or clamping. The bit depth option is ignored.
+#[cfg(feature = "exr")]
+pub fn save_exr(
+    texture: &dyn Texture,
+    path: &std::path::Path,
+    options: &SaveOptions,
+) -> Result<(), SaveError> {
+    let values = options.render(texture, options.color_space.unwrap_or(ColorSpace::Linear));
+    exr::prelude::write_rgb_file(path, options.width, options.height, |x, y| {
+        let v = values[y * options.width + x];
+        (v.x, v.y, v.z)
+    })
+    .map_err(SaveError::Exr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::map3::*;
+    use super::super::noise::*;
+    use super::*;
+
+    #[cfg(feature = "png")]
+    #[test]
+    pub fn png() {
+        let texture = noise(1, 4.0, tile_all());
+        let options = SaveOptions::new(8, 4)
+            .bit_depth(BitDepth::Sixteen)
+            .color_space(ColorSpace::Data);
+        let mut data = Vec::new();
+        write_png(&*texture, &mut data, &options).unwrap();
+        let mut reader = png::Decoder::new(&data[..]).read_info().unwrap();
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut buffer).unwrap();
+        assert_eq!((info.width, info.height), (8, 4));
+        assert_eq!(info.bit_depth, png::BitDepth::Sixteen);
+        let format = RenderFormat::new(PixelFormat::Rgb16).color_space(ColorSpace::Data);
+        let image = render_2d(&*texture, 8, 4, Region::unit(), 0.5, &format);
+        let expected: Vec<u8> = image
+            .as_u16()
+            .unwrap()
+            .iter()
+            .flat_map(|x| x.to_be_bytes())
+            .collect();
+        assert_eq!(buffer, expected);
+    }
+
+    #[cfg(feature = "exr")]
+    #[test]
+    pub fn exr() {
+        let texture = noise(2, 4.0, tile_all());
+        let options = SaveOptions::new(4, 4).color_space(ColorSpace::Data);
+        let path = std::env::temp_dir().join("funutd_test.exr");
+        save_exr(&*texture, &path, &options).unwrap();
+        let image = exr::prelude::read_first_rgba_layer_from_file(
+            &path,
+            |size, _| vec![[0.0f32; 3]; size.width() * size.height()],
+            |pixels, position, (r, g, b, _): (f32, f32, f32, f32)| {
+                pixels[position.y() * 4 + position.x()] = [r, g, b];
+            },
+        )
+        .unwrap();
+        std::fs::remove_file(&path).ok();
+        // Values are saved as is.
+        let v = texture.at(vec3a(0.375, 0.625, 0.5));
+        assert_eq!(
+            image.layer_data.channel_data.pixels[2 * 4 + 1],
+            v.to_array()
+        );
+
+        // Colors are linear by default.
+        save_exr(&zero(), &path, &SaveOptions::new(1, 1)).unwrap();
+        let image = exr::prelude::read_first_rgba_layer_from_file(
+            &path,
+            |_, _| [0.0f32; 3],
+            |pixel, _, (r, g, b, _): (f32, f32, f32, f32)| *pixel = [r, g, b],
+        )
+        .unwrap();
+        std::fs::remove_file(&path).ok();
+        assert!((image.layer_data.channel_data.pixels[0] - 0.214).abs() < 1.0e-3);
+    }
+}
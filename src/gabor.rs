@@ -0,0 +1,263 @@
+//! Gabor noise, an anisotropic sparse convolution noise.
+
+use super::hash::*;
+use super::map3base::*;
+use super::math::*;
+use super::*;
+extern crate alloc;
+use alloc::{boxed::Box, string::String};
+
+/// Orientation of Gabor kernels.
+#[derive(Clone)]
+enum Orientation {
+    /// Each kernel is oriented randomly.
+    Isotropic,
+    /// All kernels share a unit length direction.
+    Direction(Vec3a),
+    /// Kernel direction is read from the values of a texture.
+    Field(Box<dyn Texture>),
+}
+
+/// Gabor noise. Kernels are sinusoids windowed by a Gaussian envelope.
+/// Kernel frequency is in cycles per cell. Bandwidth controls the width of the envelope:
+/// low values produce long, coherent stripes while high values produce grainy noise.
+#[derive(Clone)]
+pub struct Gabor<H: Hasher> {
+    seed: u64,
+    frequency: f32,
+    kernel_frequency: f32,
+    bandwidth: f32,
+    orientation: Orientation,
+    hasher: H,
+}
+
+/// Isotropic Gabor noise.
+pub fn gabor<H: 'static + Hasher>(
+    seed: u64,
+    frequency: f32,
+    kernel_frequency: f32,
+    bandwidth: f32,
+    hasher: H,
+) -> Box<dyn Texture> {
+    Box::new(Gabor {
+        seed,
+        frequency,
+        kernel_frequency,
+        bandwidth,
+        orientation: Orientation::Isotropic,
+        hasher,
+    })
+}
+
+/// Isotropic Gabor noise basis.
+pub fn gabor_basis<H: 'static + Hasher>(
+    seed: u64,
+    kernel_frequency: f32,
+    bandwidth: f32,
+    hasher: H,
+) -> Box<dyn Texture> {
+    gabor(seed, 1.0, kernel_frequency, bandwidth, hasher)
+}
+
+/// Anisotropic Gabor noise with all kernels oriented along `direction`.
+pub fn gabor_oriented<H: 'static + Hasher>(
+    seed: u64,
+    frequency: f32,
+    kernel_frequency: f32,
+    bandwidth: f32,
+    direction: Vec3,
+    hasher: H,
+) -> Box<dyn Texture> {
+    let direction = Vec3a::from(direction);
+    let length = direction.length();
+    assert!(length > 0.0);
+    Box::new(Gabor {
+        seed,
+        frequency,
+        kernel_frequency,
+        bandwidth,
+        orientation: Orientation::Direction(direction / length),
+        hasher,
+    })
+}
+
+/// Anisotropic Gabor noise basis with all kernels oriented along `direction`.
+pub fn gabor_oriented_basis<H: 'static + Hasher>(
+    seed: u64,
+    kernel_frequency: f32,
+    bandwidth: f32,
+    direction: Vec3,
+    hasher: H,
+) -> Box<dyn Texture> {
+    gabor_oriented(seed, 1.0, kernel_frequency, bandwidth, direction, hasher)
+}
+
+/// Anisotropic Gabor noise with kernels oriented along the values of the `field` texture.
+/// The field is sampled at its own frequencies.
+pub fn gabor_field<H: 'static + Hasher>(
+    seed: u64,
+    frequency: f32,
+    kernel_frequency: f32,
+    bandwidth: f32,
+    field: Box<dyn Texture>,
+    hasher: H,
+) -> Box<dyn Texture> {
+    Box::new(Gabor {
+        seed,
+        frequency,
+        kernel_frequency,
+        bandwidth,
+        orientation: Orientation::Field(field),
+        hasher,
+    })
+}
+
+/// Anisotropic Gabor noise basis with kernels oriented along the values of the `field` texture.
+/// The field is sampled at its own frequencies.
+pub fn gabor_field_basis<H: 'static + Hasher>(
+    seed: u64,
+    kernel_frequency: f32,
+    bandwidth: f32,
+    field: Box<dyn Texture>,
+    hasher: H,
+) -> Box<dyn Texture> {
+    gabor_field(seed, 1.0, kernel_frequency, bandwidth, field, hasher)
+}
+
+impl<H: Hasher> Texture for Gabor<H> {
+    fn at_frequency(&self, point: Vec3a, frequency: Option<f32>) -> Vec3a {
+        let frequency = frequency.unwrap_or(self.frequency);
+        let basis = self.hasher.query(self.seed, frequency, point);
+        let direction = match &self.orientation {
+            Orientation::Isotropic => Vec3a::zero(),
+            Orientation::Direction(direction) => *direction,
+            Orientation::Field(field) => {
+                let v = field.at(point);
+                let length = v.length();
+                if length > 1.0e-9 {
+                    v / length
+                } else {
+                    vec3a(1.0, 0.0, 0.0)
+                }
+            }
+        };
+        let a2 = f32::PI * squared(self.bandwidth);
+        let mut result = Vec3a::zero();
+
+        for dx in -1..=1 {
+            let hx = self.hasher.hash_x(&basis, 0, dx);
+            for dy in -1..=1 {
+                let hxy = self.hasher.hash_y(&basis, hx, dy);
+                let mut offset = Vec3a::new(dx as f32, dy as f32, 0.0) - basis.d;
+                for dz in -1..=1 {
+                    let mut hash = self.hasher.hash_z(&basis, hxy, dz);
+                    // Pick number of cells as a rough approximation to a Poisson distribution.
+                    let n = match hash & 7 {
+                        0 | 1 | 2 => 1,
+                        3 | 4 | 5 => 2,
+                        _ => 3,
+                    };
+                    // Offset points from cell corner to queried point.
+                    offset = vec3a(offset.x, offset.y, dz as f32 - basis.d.z);
+                    for i in 0..n {
+                        // Feature location.
+                        let p = hash_01(hash);
+                        let delta = p + offset;
+                        let distance2: f32 = delta.length_squared();
+                        // The envelope is windowed to vanish at the maximum radius of 1.
+                        if distance2 < 1.0 {
+                            let distance = sqrt(distance2);
+                            let envelope = exp(-a2 * distance2) * (1.0 - smooth5(distance));
+                            let omega = match self.orientation {
+                                Orientation::Isotropic => hash_unit(hash64d(hash)),
+                                _ => direction,
+                            };
+                            let phase = hash_01(hash64e(hash)).x * f32::TAU;
+                            let harmonic =
+                                cos(f32::TAU * self.kernel_frequency * omega.dot(delta) + phase);
+                            let color = hash_11(hash);
+                            result += color * (envelope * harmonic);
+                        }
+                        if i + 1 < n {
+                            hash = hash64c(hash);
+                        }
+                    }
+                }
+            }
+        }
+        // Normalize deviation roughly; the envelope narrows as bandwidth increases.
+        result * (1.0 + self.bandwidth * (0.25 + 0.5 * self.bandwidth))
+    }
+
+    fn estimated_cost(&self) -> f32 {
+        let field = match &self.orientation {
+            Orientation::Field(field) => field.estimated_cost(),
+            _ => 0.0,
+        };
+        1.4 + field
+    }
+
+    fn get_code(&self) -> String {
+        match &self.orientation {
+            Orientation::Isotropic => format!(
+                "gabor({}, {:?}, {:?}, {:?}, {})",
+                self.seed,
+                self.frequency,
+                self.kernel_frequency,
+                self.bandwidth,
+                self.hasher.get_code()
+            ),
+            Orientation::Direction(direction) => format!(
+                "gabor_oriented({}, {:?}, {:?}, {:?}, vec3({:?}, {:?}, {:?}), {})",
+                self.seed,
+                self.frequency,
+                self.kernel_frequency,
+                self.bandwidth,
+                direction.x,
+                direction.y,
+                direction.z,
+                self.hasher.get_code()
+            ),
+            Orientation::Field(field) => format!(
+                "gabor_field({}, {:?}, {:?}, {:?}, {}, {})",
+                self.seed,
+                self.frequency,
+                self.kernel_frequency,
+                self.bandwidth,
+                field.get_code(),
+                self.hasher.get_code()
+            ),
+        }
+    }
+
+    fn get_basis_code(&self) -> String {
+        match &self.orientation {
+            Orientation::Isotropic => format!(
+                "gabor_basis({}, {:?}, {:?}, {})",
+                self.seed,
+                self.kernel_frequency,
+                self.bandwidth,
+                self.hasher.get_code()
+            ),
+            Orientation::Direction(direction) => format!(
+                "gabor_oriented_basis({}, {:?}, {:?}, vec3({:?}, {:?}, {:?}), {})",
+                self.seed,
+                self.kernel_frequency,
+                self.bandwidth,
+                direction.x,
+                direction.y,
+                direction.z,
+                self.hasher.get_code()
+            ),
+            // The field is sampled at its own frequencies, so its full code is needed.
+            Orientation::Field(field) => format!(
+                "gabor_field_basis({}, {:?}, {:?}, {}, {})",
+                self.seed,
+                self.kernel_frequency,
+                self.bandwidth,
+                field.get_code(),
+                self.hasher.get_code()
+            ),
+        }
+    }
+}
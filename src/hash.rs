@@ -148,3 +148,15 @@ pub fn hash128b(x: u128) -> u64 {
     let a = (a ^ (a >> 44)).wrapping_mul(C);
     (a ^ (a >> 41)).wrapping_mul(C)
 }
+
+/// 64-bit hash of a string. The algorithm is fixed:
+/// results do not depend on platform, target features or dependency versions.
+pub fn hash_str(s: &str) -> u64 {
+    let mut hash = hash64a(s.len() as u64);
+    for chunk in s.as_bytes().chunks(8) {
+        let mut bytes = [0u8; 8];
+        bytes[..chunk.len()].copy_from_slice(chunk);
+        hash = hash128a(((hash as u128) << 64) | u64::from_le_bytes(bytes) as u128);
+    }
+    hash
+}
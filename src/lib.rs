@@ -2,6 +2,9 @@
 #![cfg_attr(not(feature = "std"), no_std)]
 #[macro_use]
 extern crate alloc;
+// Lets derived implementations refer to the crate by name inside the crate.
+#[cfg(feature = "derive")]
+extern crate self as funutd;
 
 #[allow(non_snake_case)]
 #[allow(clippy::excessive_precision)]
@@ -11,6 +14,11 @@ pub mod distance;
 #[allow(clippy::too_many_arguments)]
 pub mod dna;
 pub mod ease;
+pub mod evolve;
+#[cfg(any(feature = "png", feature = "exr"))]
+pub mod export;
+#[allow(clippy::manual_range_patterns)]
+pub mod gabor;
 #[allow(non_snake_case)]
 pub mod hash;
 pub mod lcg;
@@ -22,8 +30,14 @@ pub mod map3gen;
 pub mod math;
 #[allow(clippy::manual_range_patterns)]
 pub mod noise;
+pub mod novelty;
+pub mod pattern;
 pub mod prelude;
+pub mod render;
 pub mod rnd;
+#[allow(clippy::too_many_arguments)]
+pub mod spots;
+pub mod tiles;
 pub mod vec;
 #[allow(clippy::too_many_arguments)]
 #[allow(clippy::manual_range_patterns)]
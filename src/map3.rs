@@ -16,6 +16,9 @@ impl Texture for Zero {
     fn at_frequency(&self, _point: Vec3a, _frequency: Option<f32>) -> Vec3a {
         Vec3a::zero()
     }
+    fn estimated_cost(&self) -> f32 {
+        0.0
+    }
     fn get_code(&self) -> String {
         "zero()".to_string()
     }
@@ -41,6 +44,9 @@ impl Texture for Saturate {
     fn at_frequency(&self, point: Vec3a, frequency: Option<f32>) -> Vec3a {
         softsign(self.texture.at_frequency(point, frequency) * self.amount)
     }
+    fn estimated_cost(&self) -> f32 {
+        0.02 + self.texture.estimated_cost()
+    }
     fn get_code(&self) -> String {
         format!("saturate({}, {})", self.amount, self.texture.get_code())
     }
@@ -76,6 +82,9 @@ impl Texture for Reflect {
             self.offset + self.texture.at_frequency(point, frequency) * self.amount,
         )
     }
+    fn estimated_cost(&self) -> f32 {
+        0.05 + self.texture.estimated_cost()
+    }
     fn get_code(&self) -> String {
         format!(
             "reflect({}, vec3({:?}, {:?}, {:?}), {})",
@@ -135,6 +144,9 @@ impl Texture for Posterize {
             Vec3a::zero()
         }
     }
+    fn estimated_cost(&self) -> f32 {
+        0.15 + self.texture.estimated_cost()
+    }
     fn get_code(&self) -> String {
         format!(
             "posterize({}, {}, {})",
@@ -182,6 +194,9 @@ impl Texture for Overdrive {
             Vec3a::zero()
         }
     }
+    fn estimated_cost(&self) -> f32 {
+        0.02 + self.texture.estimated_cost()
+    }
     fn get_code(&self) -> String {
         format!("overdrive({}, {})", self.amount, self.texture.get_code())
     }
@@ -219,6 +234,9 @@ impl Texture for VReflect {
             Vec3a::zero()
         }
     }
+    fn estimated_cost(&self) -> f32 {
+        0.02 + self.texture.estimated_cost()
+    }
     fn get_code(&self) -> String {
         format!("vreflect({}, {})", self.amount, self.texture.get_code())
     }
@@ -257,6 +275,9 @@ impl Texture for Rotate {
             Vec3a::zero()
         }
     }
+    fn estimated_cost(&self) -> f32 {
+        0.02 + self.texture_a.estimated_cost() + self.texture_b.estimated_cost()
+    }
     fn get_code(&self) -> String {
         format!(
             "rotate({:?}, {}, {})",
@@ -310,6 +331,9 @@ impl Texture for Softmix3 {
         let epsilon: f32 = 1.0e-9;
         (v * vw + u * uw) / (vw + uw + epsilon)
     }
+    fn estimated_cost(&self) -> f32 {
+        0.02 + self.texture_a.estimated_cost() + self.texture_b.estimated_cost()
+    }
     fn get_code(&self) -> String {
         format!(
             "softmix3({:?}, {:?}, {}, {})",
@@ -366,6 +390,9 @@ impl Texture for Layer {
             u
         }
     }
+    fn estimated_cost(&self) -> f32 {
+        0.02 + self.texture_a.estimated_cost() + self.texture_b.estimated_cost()
+    }
     fn get_code(&self) -> String {
         format!(
             "layer({:?}, Ease::{:?}, {}, {})",
@@ -417,6 +444,9 @@ impl Texture for Displace {
             frequency,
         )
     }
+    fn estimated_cost(&self) -> f32 {
+        0.02 + self.texture_a.estimated_cost() + self.texture_b.estimated_cost()
+    }
     fn get_code(&self) -> String {
         format!(
             "displace({:?}, {}, {})",
@@ -498,6 +528,9 @@ impl Texture for Fractal {
         }
         result / sqrt(total_w)
     }
+    fn estimated_cost(&self) -> f32 {
+        self.octaves as f32 * (0.05 + self.texture.estimated_cost())
+    }
     fn get_code(&self) -> String {
         format!(
             "fractal({:?}, {}, {}, {:?}, {:?}, {:?}, {:?}, {})",
@@ -554,6 +587,9 @@ impl Texture for Shift {
         let p = (self.rotation * (v - self.origin)) + self.origin;
         vec3a(sin(p.x), sin(p.y), sin(p.z))
     }
+    fn estimated_cost(&self) -> f32 {
+        0.05 + self.texture.estimated_cost()
+    }
     fn get_code(&self) -> String {
         format!("shift({}, {})", self.seed, self.texture.get_code())
     }
@@ -578,3 +614,456 @@ pub fn shift(seed: u32, texture: Box<dyn Texture>) -> Box<dyn Texture> {
         texture,
     })
 }
+
+/// Position of `point` relative to the center of its unit cell, in -0.5...0.5.
+#[inline]
+fn cell_offset(point: Vec3a) -> Vec3a {
+    point - point.floor() - Vec3a::splat(0.5)
+}
+
+/// Mirrors space around the center of each unit cell on chosen axes.
+#[derive(Clone)]
+pub struct Mirror {
+    x: bool,
+    y: bool,
+    z: bool,
+    texture: Box<dyn Texture>,
+}
+
+impl Texture for Mirror {
+    fn at_frequency(&self, point: Vec3a, frequency: Option<f32>) -> Vec3a {
+        // Folding with a triangle wave keeps the result continuous.
+        let fold = |x: f32| abs(x - x.round());
+        let p = vec3a(
+            if self.x { fold(point.x) } else { point.x },
+            if self.y { fold(point.y) } else { point.y },
+            if self.z { fold(point.z) } else { point.z },
+        );
+        self.texture.at_frequency(p, frequency)
+    }
+    fn estimated_cost(&self) -> f32 {
+        0.02 + self.texture.estimated_cost()
+    }
+    fn get_code(&self) -> String {
+        format!(
+            "mirror({}, {}, {}, {})",
+            self.x,
+            self.y,
+            self.z,
+            self.texture.get_code()
+        )
+    }
+    fn get_basis_code(&self) -> String {
+        format!(
+            "mirror({}, {}, {}, {})",
+            self.x,
+            self.y,
+            self.z,
+            self.texture.get_basis_code()
+        )
+    }
+}
+
+/// Mirrors space on the chosen axes. Mirror planes are placed at integer
+/// and half-integer coordinates, so the result repeats at unit intervals
+/// and is symmetric around the center of the unit cell.
+pub fn mirror(x: bool, y: bool, z: bool, texture: Box<dyn Texture>) -> Box<dyn Texture> {
+    Box::new(Mirror { x, y, z, texture })
+}
+
+/// Folds space around an axis through the center of each unit cell.
+#[derive(Clone)]
+pub struct Kaleidoscope {
+    segments: u32,
+    axis: Axis,
+    mirrored: bool,
+    texture: Box<dyn Texture>,
+}
+
+impl Texture for Kaleidoscope {
+    fn at_frequency(&self, point: Vec3a, frequency: Option<f32>) -> Vec3a {
+        let (u, v) = self.axis.across(cell_offset(point));
+        let r = sqrt(u * u + v * v);
+        let wedge = f32::TAU / self.segments as f32;
+        let angle = libm::atan2f(v, u);
+        let mut a = angle - floor(angle / wedge) * wedge;
+        if self.mirrored && a > 0.5 * wedge {
+            a = wedge - a;
+        }
+        let p = self
+            .axis
+            .compose(self.axis.along(point), (0.5 + r * cos(a), 0.5 + r * sin(a)));
+        self.texture.at_frequency(p, frequency)
+    }
+    fn estimated_cost(&self) -> f32 {
+        0.05 + self.texture.estimated_cost()
+    }
+    fn get_code(&self) -> String {
+        format!(
+            "{}({}, {}, {})",
+            if self.mirrored {
+                "kaleidoscope"
+            } else {
+                "radial_repeat"
+            },
+            self.segments,
+            self.axis.get_code(),
+            self.texture.get_code()
+        )
+    }
+    fn get_basis_code(&self) -> String {
+        format!(
+            "{}({}, {}, {})",
+            if self.mirrored {
+                "kaleidoscope"
+            } else {
+                "radial_repeat"
+            },
+            self.segments,
+            self.axis.get_code(),
+            self.texture.get_basis_code()
+        )
+    }
+}
+
+/// Kaleidoscope: divides the plane perpendicular to `axis` into `segments` (segments > 0)
+/// wedges around the center of each unit cell. Every other wedge is mirrored, which keeps
+/// the result continuous. The result repeats at unit intervals in the plane.
+pub fn kaleidoscope(segments: u32, axis: Axis, texture: Box<dyn Texture>) -> Box<dyn Texture> {
+    assert!(segments > 0);
+    Box::new(Kaleidoscope {
+        segments,
+        axis,
+        mirrored: true,
+        texture,
+    })
+}
+
+/// Repeats a wedge of the plane perpendicular to `axis` `segments` times (segments > 0)
+/// around the center of each unit cell, with rotational symmetry.
+/// Wedge edges may show seams. The result repeats at unit intervals in the plane.
+pub fn radial_repeat(segments: u32, axis: Axis, texture: Box<dyn Texture>) -> Box<dyn Texture> {
+    assert!(segments > 0);
+    Box::new(Kaleidoscope {
+        segments,
+        axis,
+        mirrored: false,
+        texture,
+    })
+}
+
+/// Looks up a texture in polar or cylindrical coordinates.
+#[derive(Clone)]
+pub struct Polar {
+    axis: Axis,
+    cylindrical: bool,
+    texture: Box<dyn Texture>,
+}
+
+impl Texture for Polar {
+    fn at_frequency(&self, point: Vec3a, frequency: Option<f32>) -> Vec3a {
+        let (u, v) = self.axis.across(cell_offset(point));
+        let angle = libm::atan2f(v, u) / f32::TAU + 0.5;
+        let r = sqrt(u * u + v * v);
+        let h = self.axis.along(point);
+        let p = if self.cylindrical {
+            vec3a(angle, r, h)
+        } else {
+            self.axis.compose(h, (angle, r))
+        };
+        self.texture.at_frequency(p, frequency)
+    }
+    fn estimated_cost(&self) -> f32 {
+        0.05 + self.texture.estimated_cost()
+    }
+    fn get_code(&self) -> String {
+        format!(
+            "{}({}, {})",
+            if self.cylindrical {
+                "to_cylindrical"
+            } else {
+                "to_polar"
+            },
+            self.axis.get_code(),
+            self.texture.get_code()
+        )
+    }
+    fn get_basis_code(&self) -> String {
+        format!(
+            "{}({}, {})",
+            if self.cylindrical {
+                "to_cylindrical"
+            } else {
+                "to_polar"
+            },
+            self.axis.get_code(),
+            self.texture.get_basis_code()
+        )
+    }
+}
+
+/// Looks up a texture in polar coordinates around an axis through the center of each unit cell.
+/// In the plane perpendicular to `axis`, the first coordinate is replaced with angle in 0...1
+/// and the second with distance from the center. The axis coordinate is retained.
+/// The angle wraps seamlessly if the texture tiles.
+pub fn to_polar(axis: Axis, texture: Box<dyn Texture>) -> Box<dyn Texture> {
+    Box::new(Polar {
+        axis,
+        cylindrical: false,
+        texture,
+    })
+}
+
+/// Looks up a texture in cylindrical coordinates around an axis through the center of each
+/// unit cell. The texture is queried at (angle, radius, height), where angle is in 0...1.
+/// The angle wraps seamlessly if the texture tiles.
+pub fn to_cylindrical(axis: Axis, texture: Box<dyn Texture>) -> Box<dyn Texture> {
+    Box::new(Polar {
+        axis,
+        cylindrical: true,
+        texture,
+    })
+}
+
+/// Looks up a texture in spherical coordinates around the center of each unit cell.
+#[derive(Clone)]
+pub struct Spherical {
+    texture: Box<dyn Texture>,
+}
+
+impl Texture for Spherical {
+    fn at_frequency(&self, point: Vec3a, frequency: Option<f32>) -> Vec3a {
+        let c = cell_offset(point);
+        let r = c.length();
+        let azimuth = libm::atan2f(c.y, c.x) / f32::TAU + 0.5;
+        let inclination = if r > 0.0 {
+            libm::acosf(clamp11(c.z / r)) / f32::PI
+        } else {
+            0.0
+        };
+        self.texture
+            .at_frequency(vec3a(azimuth, inclination, r), frequency)
+    }
+    fn estimated_cost(&self) -> f32 {
+        0.05 + self.texture.estimated_cost()
+    }
+    fn get_code(&self) -> String {
+        format!("to_spherical({})", self.texture.get_code())
+    }
+    fn get_basis_code(&self) -> String {
+        format!("to_spherical({})", self.texture.get_basis_code())
+    }
+}
+
+/// Looks up a texture in spherical coordinates around the center of each unit cell.
+/// The texture is queried at (azimuth, inclination, radius), where azimuth and
+/// inclination are in 0...1. The azimuth wraps seamlessly if the texture tiles.
+pub fn to_spherical(texture: Box<dyn Texture>) -> Box<dyn Texture> {
+    Box::new(Spherical { texture })
+}
+
+/// Maps the X component of a texture to anchor weights of a `palette`.
+#[derive(Clone)]
+pub struct Ramp {
+    bias: f32,
+    contrast: f32,
+    texture: Box<dyn Texture>,
+}
+
+impl Texture for Ramp {
+    fn at_frequency(&self, point: Vec3a, frequency: Option<f32>) -> Vec3a {
+        let x = self.texture.at_frequency(point, frequency).x;
+        let t = clamp11(x * self.contrast + self.bias) + 1.0;
+        let w1 = max(0.0, 1.0 - t);
+        let w3 = max(0.0, t - 1.0);
+        vec3a(w1, 1.0 - w1 - w3, w3) * 2.0 - Vec3a::one()
+    }
+    fn estimated_cost(&self) -> f32 {
+        0.02 + self.texture.estimated_cost()
+    }
+    fn get_code(&self) -> String {
+        format!(
+            "ramp({:?}, {:?}, {})",
+            self.bias,
+            self.contrast,
+            self.texture.get_code()
+        )
+    }
+    fn get_basis_code(&self) -> String {
+        format!(
+            "ramp({:?}, {:?}, {})",
+            self.bias,
+            self.contrast,
+            self.texture.get_basis_code()
+        )
+    }
+}
+
+/// Maps the X component of a texture to anchor weights of a `palette`, for coloring
+/// scalar patterns. The component is scaled by `contrast` and offset by `bias`.
+/// Then values from -1 to 1 blend from the first anchor through the second to the third.
+pub fn ramp(bias: f32, contrast: f32, texture: Box<dyn Texture>) -> Box<dyn Texture> {
+    Box::new(Ramp {
+        bias,
+        contrast,
+        texture,
+    })
+}
+
+/// Mixes the components of a texture into a scalar.
+#[derive(Clone)]
+pub struct Mono {
+    weights: Vec3,
+    bias: f32,
+    texture: Box<dyn Texture>,
+}
+
+impl Texture for Mono {
+    fn at_frequency(&self, point: Vec3a, frequency: Option<f32>) -> Vec3a {
+        let v = self.texture.at_frequency(point, frequency);
+        Vec3a::splat(clamp11(Vec3a::from(self.weights).dot(v) + self.bias))
+    }
+    fn estimated_cost(&self) -> f32 {
+        0.02 + self.texture.estimated_cost()
+    }
+    fn get_code(&self) -> String {
+        format!(
+            "mono(vec3({:?}, {:?}, {:?}), {:?}, {})",
+            self.weights.x,
+            self.weights.y,
+            self.weights.z,
+            self.bias,
+            self.texture.get_code()
+        )
+    }
+    fn get_basis_code(&self) -> String {
+        format!(
+            "mono(vec3({:?}, {:?}, {:?}), {:?}, {})",
+            self.weights.x,
+            self.weights.y,
+            self.weights.z,
+            self.bias,
+            self.texture.get_basis_code()
+        )
+    }
+}
+
+/// Mixes the components of a texture into a scalar, for deriving material channels.
+/// The scalar is the dot product with `weights` offset by `bias`, clamped to -1...1,
+/// and is repeated in all components.
+pub fn mono(weights: Vec3, bias: f32, texture: Box<dyn Texture>) -> Box<dyn Texture> {
+    Box::new(Mono {
+        weights,
+        bias,
+        texture,
+    })
+}
+
+/// Tangent space normal from a height texture.
+#[derive(Clone)]
+pub struct NormalMap {
+    strength: f32,
+    delta: f32,
+    texture: Box<dyn Texture>,
+}
+
+impl Texture for NormalMap {
+    fn at_frequency(&self, point: Vec3a, frequency: Option<f32>) -> Vec3a {
+        let height = |dx: f32, dy: f32| {
+            self.texture
+                .at_frequency(point + vec3a(dx, dy, 0.0), frequency)
+                .x
+        };
+        let scale = self.strength * 0.5 / self.delta;
+        let dx = (height(self.delta, 0.0) - height(-self.delta, 0.0)) * scale;
+        let dy = (height(0.0, self.delta) - height(0.0, -self.delta)) * scale;
+        vec3a(-dx, -dy, 1.0).normalize()
+    }
+    fn estimated_cost(&self) -> f32 {
+        0.1 + 4.0 * self.texture.estimated_cost()
+    }
+    fn get_code(&self) -> String {
+        format!(
+            "normal_map({:?}, {:?}, {})",
+            self.strength,
+            self.delta,
+            self.texture.get_code()
+        )
+    }
+    fn get_basis_code(&self) -> String {
+        format!(
+            "normal_map({:?}, {:?}, {})",
+            self.strength,
+            self.delta,
+            self.texture.get_basis_code()
+        )
+    }
+}
+
+/// Tangent space normal from the X component of a height texture, viewed in XY slices.
+/// Height spans `strength` units from -1 to 1. Slopes are estimated from central differences
+/// at distance `delta` (`delta` > 0), which should be about the size of a pixel.
+/// Components of the unit normal are returned as is.
+pub fn normal_map(strength: f32, delta: f32, texture: Box<dyn Texture>) -> Box<dyn Texture> {
+    Box::new(NormalMap {
+        strength,
+        delta,
+        texture,
+    })
+}
+
+/// Number of height samples around the point in `Occlusion`.
+const OCCLUSION_SAMPLES: usize = 8;
+
+/// Ambient occlusion from a height texture.
+#[derive(Clone)]
+pub struct Occlusion {
+    radius: f32,
+    strength: f32,
+    texture: Box<dyn Texture>,
+}
+
+impl Texture for Occlusion {
+    fn at_frequency(&self, point: Vec3a, frequency: Option<f32>) -> Vec3a {
+        let h = self.texture.at_frequency(point, frequency).x;
+        let mut occlusion = 0.0;
+        for i in 0..OCCLUSION_SAMPLES {
+            let a = i as f32 * f32::TAU / OCCLUSION_SAMPLES as f32;
+            let p = point + vec3a(cos(a), sin(a), 0.0) * self.radius;
+            occlusion += max(0.0, self.texture.at_frequency(p, frequency).x - h);
+        }
+        let light = clamp01(1.0 - occlusion * self.strength / OCCLUSION_SAMPLES as f32);
+        Vec3a::splat(light * 2.0 - 1.0)
+    }
+    fn estimated_cost(&self) -> f32 {
+        0.1 + (OCCLUSION_SAMPLES + 1) as f32 * self.texture.estimated_cost()
+    }
+    fn get_code(&self) -> String {
+        format!(
+            "occlusion({:?}, {:?}, {})",
+            self.radius,
+            self.strength,
+            self.texture.get_code()
+        )
+    }
+    fn get_basis_code(&self) -> String {
+        format!(
+            "occlusion({:?}, {:?}, {})",
+            self.radius,
+            self.strength,
+            self.texture.get_basis_code()
+        )
+    }
+}
+
+/// Ambient occlusion from the X component of a height texture, viewed in XY slices.
+/// Points lower than their surroundings at distance `radius` are occluded
+/// in proportion to `strength`. Returns -1 for full occlusion and 1 for none
+/// in all components.
+pub fn occlusion(radius: f32, strength: f32, texture: Box<dyn Texture>) -> Box<dyn Texture> {
+    Box::new(Occlusion {
+        radius,
+        strength,
+        texture,
+    })
+}
@@ -5,7 +5,7 @@ use super::math::*;
 use super::*;
 use dyn_clone::DynClone;
 extern crate alloc;
-use alloc::string::String;
+use alloc::{string::String, sync::Arc};
 
 /// Bases attach a feature grid to a queried point.
 /// Once attached, the grid is resolution independent:
@@ -303,6 +303,65 @@ impl Hasher for TileZ {
     }
 }
 
+/// Object safe counterpart of `Hasher`, implemented for all hashers.
+trait DynHasher: Sync + Send {
+    fn query(&self, seed: u64, frequency: f32, point: Vec3a) -> Basis;
+    fn hash_x(&self, basis: &Basis, previous: u64, dx: i32) -> u64;
+    fn hash_y(&self, basis: &Basis, previous: u64, dy: i32) -> u64;
+    fn hash_z(&self, basis: &Basis, previous: u64, dz: i32) -> u64;
+    fn get_code(&self) -> String;
+}
+
+impl<H: Hasher> DynHasher for H {
+    fn query(&self, seed: u64, frequency: f32, point: Vec3a) -> Basis {
+        Hasher::query(self, seed, frequency, point)
+    }
+    fn hash_x(&self, basis: &Basis, previous: u64, dx: i32) -> u64 {
+        Hasher::hash_x(self, basis, previous, dx)
+    }
+    fn hash_y(&self, basis: &Basis, previous: u64, dy: i32) -> u64 {
+        Hasher::hash_y(self, basis, previous, dy)
+    }
+    fn hash_z(&self, basis: &Basis, previous: u64, dz: i32) -> u64 {
+        Hasher::hash_z(self, basis, previous, dz)
+    }
+    fn get_code(&self) -> String {
+        Hasher::get_code(self)
+    }
+}
+
+/// Hasher of any type. Custom generator nodes receive their hasher in this form.
+#[derive(Clone)]
+pub struct AnyHasher {
+    hasher: Arc<dyn DynHasher>,
+}
+
+impl AnyHasher {
+    pub fn new<H: 'static + Hasher>(hasher: H) -> Self {
+        Self {
+            hasher: Arc::new(hasher),
+        }
+    }
+}
+
+impl Hasher for AnyHasher {
+    fn query(&self, seed: u64, frequency: f32, point: Vec3a) -> Basis {
+        self.hasher.query(seed, frequency, point)
+    }
+    fn hash_x(&self, basis: &Basis, current: u64, dx: i32) -> u64 {
+        self.hasher.hash_x(basis, current, dx)
+    }
+    fn hash_y(&self, basis: &Basis, current: u64, dy: i32) -> u64 {
+        self.hasher.hash_y(basis, current, dy)
+    }
+    fn hash_z(&self, basis: &Basis, current: u64, dz: i32) -> u64 {
+        self.hasher.hash_z(basis, current, dz)
+    }
+    fn get_code(&self) -> String {
+        self.hasher.get_code()
+    }
+}
+
 /// Textures are self-maps in 3-space.
 pub trait Texture: Sync + Send + DynClone {
     /// Evaluate texture at `point` using `frequency` for basis frequencies.
@@ -322,6 +381,13 @@ pub trait Texture: Sync + Send + DynClone {
 
     /// Get code for instantiating this texture without specifying basis frequencies.
     fn get_basis_code(&self) -> String;
+
+    /// Estimated cost of evaluating the texture at a point, relative to gradient noise.
+    /// Multiply by the measured time of gradient noise per megapixel to estimate rendering time.
+    /// The default is the cost of gradient noise.
+    fn estimated_cost(&self) -> f32 {
+        1.0
+    }
 }
 
 dyn_clone::clone_trait_object!(Texture);
@@ -334,3 +400,58 @@ pub enum TilingMode {
     XY,
     All,
 }
+
+impl core::str::FromStr for TilingMode {
+    type Err = ();
+    /// Parses the variant name as formatted by `Debug`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "None" => Ok(TilingMode::None),
+            "Z" => Ok(TilingMode::Z),
+            "XY" => Ok(TilingMode::XY),
+            "All" => Ok(TilingMode::All),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Coordinate axes.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Axis {
+    X,
+    Y,
+    Z,
+}
+
+impl Axis {
+    /// Component of `v` along the axis.
+    #[inline]
+    pub fn along(&self, v: Vec3a) -> f32 {
+        match self {
+            Axis::X => v.x,
+            Axis::Y => v.y,
+            Axis::Z => v.z,
+        }
+    }
+    /// Components of `v` in the plane perpendicular to the axis, in cyclic order.
+    #[inline]
+    pub fn across(&self, v: Vec3a) -> (f32, f32) {
+        match self {
+            Axis::X => (v.y, v.z),
+            Axis::Y => (v.z, v.x),
+            Axis::Z => (v.x, v.y),
+        }
+    }
+    /// Inverse of `along` and `across`: builds a vector from its components.
+    #[inline]
+    pub fn compose(&self, along: f32, across: (f32, f32)) -> Vec3a {
+        match self {
+            Axis::X => vec3a(along, across.0, across.1),
+            Axis::Y => vec3a(across.1, along, across.0),
+            Axis::Z => vec3a(across.0, across.1, along),
+        }
+    }
+    pub fn get_code(&self) -> String {
+        format!("Axis::{:?}", self)
+    }
+}
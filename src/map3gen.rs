@@ -4,18 +4,413 @@ use super::color::*;
 use super::distance::*;
 use super::dna::*;
 use super::ease::*;
+use super::gabor::*;
 use super::map3::*;
 use super::map3base::*;
 use super::math::*;
 use super::noise::*;
+use super::pattern::*;
+use super::spots::*;
+use super::tiles::*;
 use super::voronoi::*;
 use super::*;
 extern crate alloc;
-use alloc::boxed::Box;
+use alloc::{boxed::Box, string::String, sync::Arc, vec::Vec};
+
+/// Version of the generators in this module, for recording in Dna file headers.
+/// Incremented whenever the textures generated from existing genomes change.
+pub const GENERATOR_VERSION: u32 = 1;
+
+/// Weights of bases in generated textures. A weight of zero disallows the basis.
+/// Bases newer than the original generator default to zero, so that default
+/// configurations reproduce saved genomes; give them a weight to opt in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BasisWeights {
+    pub gradient_noise: f32,
+    pub value_noise: f32,
+    pub voronoi: f32,
+    pub camo: f32,
+    pub gabor: f32,
+    pub stripes: f32,
+    pub rings: f32,
+    pub spherical_waves: f32,
+    pub spots: f32,
+    pub truchet: f32,
+}
+
+impl Default for BasisWeights {
+    fn default() -> Self {
+        Self {
+            gradient_noise: 1.0,
+            value_noise: 1.0,
+            voronoi: 1.0,
+            camo: 0.5,
+            gabor: 0.0,
+            stripes: 0.0,
+            rings: 0.0,
+            spherical_waves: 0.0,
+            spots: 0.0,
+            truchet: 0.0,
+        }
+    }
+}
+
+/// Weights of unary nodes in generated textures. A weight of zero disallows the node.
+/// The symmetry and coordinate nodes default to zero; give them a weight to opt in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct UnaryWeights {
+    pub saturate: f32,
+    pub posterize: f32,
+    pub overdrive: f32,
+    pub vreflect: f32,
+    pub reflect: f32,
+    pub shift: f32,
+    pub mirror: f32,
+    pub kaleidoscope: f32,
+    pub radial_repeat: f32,
+    /// Polar, cylindrical and spherical coordinates.
+    pub coordinates: f32,
+}
+
+impl Default for UnaryWeights {
+    fn default() -> Self {
+        Self {
+            saturate: 1.0,
+            posterize: 1.0,
+            overdrive: 1.0,
+            vreflect: 1.0,
+            reflect: 2.0,
+            shift: 3.0,
+            mirror: 0.0,
+            kaleidoscope: 0.0,
+            radial_repeat: 0.0,
+            coordinates: 0.0,
+        }
+    }
+}
+
+/// Weights of binary nodes in generated textures. A weight of zero disallows the node.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BinaryWeights {
+    pub rotate: f32,
+    pub softmix: f32,
+    pub layer: f32,
+    pub displace: f32,
+}
+
+impl Default for BinaryWeights {
+    fn default() -> Self {
+        Self {
+            rotate: 1.0,
+            softmix: 1.0,
+            layer: 1.0,
+            displace: 2.0,
+        }
+    }
+}
+
+/// Constructs a custom basis from seed, frequency and hasher. Parameters may be drawn from the Dna.
+pub type BasisFn = dyn Fn(u64, f32, AnyHasher, &mut Dna) -> Box<dyn Texture> + Send + Sync;
+
+/// Constructs a custom unary node from a child. Parameters may be drawn from the Dna.
+pub type UnaryFn = dyn Fn(Box<dyn Texture>, &mut Dna) -> Box<dyn Texture> + Send + Sync;
+
+/// Constructs a custom binary node from two children. Parameters may be drawn from the Dna.
+pub type BinaryFn =
+    dyn Fn(Box<dyn Texture>, Box<dyn Texture>, &mut Dna) -> Box<dyn Texture> + Send + Sync;
+
+/// Custom node in a `NodeRegistry`.
+pub struct CustomNode<F: ?Sized> {
+    name: String,
+    weight: f32,
+    cost: f32,
+    constructor: Arc<F>,
+}
+
+impl<F: ?Sized> Clone for CustomNode<F> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            weight: self.weight,
+            cost: self.cost,
+            constructor: self.constructor.clone(),
+        }
+    }
+}
+
+impl<F: ?Sized> core::fmt::Debug for CustomNode<F> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("CustomNode")
+            .field("name", &self.name)
+            .field("weight", &self.weight)
+            .field("cost", &self.cost)
+            .finish()
+    }
+}
+
+impl<F: ?Sized> CustomNode<F> {
+    /// Name of the node, which is the choice recorded in the Dna.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Weight of the node relative to the built-in nodes of the same type.
+    pub fn weight(&self) -> f32 {
+        self.weight
+    }
+
+    /// Maximum estimated cost of the node, not including children,
+    /// relative to gradient noise (see `Texture::estimated_cost`).
+    pub fn cost(&self) -> f32 {
+        self.cost
+    }
+}
+
+/// Custom nodes mixed into generated textures alongside the built-in nodes.
+/// Names are recorded in the Dna and should be distinct from built-in node names.
+/// Adding nodes changes textures generated from existing genomes.
+#[derive(Clone, Debug, Default)]
+pub struct NodeRegistry {
+    basis: Vec<CustomNode<BasisFn>>,
+    unary: Vec<CustomNode<UnaryFn>>,
+    binary: Vec<CustomNode<BinaryFn>>,
+}
+
+impl NodeRegistry {
+    /// Empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a custom basis. The constructor receives the seed, frequency and hasher of the basis.
+    /// `cost` is the maximum estimated cost of the basis, which counts toward `GenConfig::max_cost`.
+    pub fn add_basis<F>(mut self, name: &str, weight: f32, cost: f32, constructor: F) -> Self
+    where
+        F: Fn(u64, f32, AnyHasher, &mut Dna) -> Box<dyn Texture> + Send + Sync + 'static,
+    {
+        self.basis.push(CustomNode {
+            name: name.into(),
+            weight,
+            cost,
+            constructor: Arc::new(constructor),
+        });
+        self
+    }
+
+    /// Add a custom unary node. `cost` is the maximum estimated cost of the node
+    /// without its child, which counts toward `GenConfig::max_cost`.
+    pub fn add_unary<F>(mut self, name: &str, weight: f32, cost: f32, constructor: F) -> Self
+    where
+        F: Fn(Box<dyn Texture>, &mut Dna) -> Box<dyn Texture> + Send + Sync + 'static,
+    {
+        self.unary.push(CustomNode {
+            name: name.into(),
+            weight,
+            cost,
+            constructor: Arc::new(constructor),
+        });
+        self
+    }
+
+    /// Add a custom binary node. `cost` is the maximum estimated cost of the node
+    /// without its children, which counts toward `GenConfig::max_cost`.
+    pub fn add_binary<F>(mut self, name: &str, weight: f32, cost: f32, constructor: F) -> Self
+    where
+        F: Fn(Box<dyn Texture>, Box<dyn Texture>, &mut Dna) -> Box<dyn Texture>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.binary.push(CustomNode {
+            name: name.into(),
+            weight,
+            cost,
+            constructor: Arc::new(constructor),
+        });
+        self
+    }
+
+    /// Custom bases.
+    pub fn basis(&self) -> &[CustomNode<BasisFn>] {
+        &self.basis
+    }
+
+    /// Custom unary nodes.
+    pub fn unary(&self) -> &[CustomNode<UnaryFn>] {
+        &self.unary
+    }
+
+    /// Custom binary nodes.
+    pub fn binary(&self) -> &[CustomNode<BinaryFn>] {
+        &self.binary
+    }
+}
+
+/// Estimated cost of unary nodes, not including the child.
+const UNARY_COST: f32 = 0.15;
+
+/// Estimated cost of binary nodes, not including the children.
+const BINARY_COST: f32 = 0.02;
+
+/// Estimated cost of a fractal octave, not including the child.
+const FRACTAL_COST: f32 = 0.05;
+
+/// Estimated cost of the palette in `genmap3palette`, not including the child.
+const PALETTE_COST: f32 = 0.05;
+
+/// Smallest budget that admits nodes other than bases.
+const MIN_INNER_BUDGET: f32 = 2.0;
+
+/// Configuration of the texture generators. The default reproduces `genmap3` and `genmap3palette`.
+/// Each group of weights must allow at least one choice; see `GenConfig::validate`.
+#[derive(Clone, Debug)]
+pub struct GenConfig {
+    /// Multiplier of the complexity dependent weight of basis nodes.
+    pub basis_weight: f32,
+    /// Multiplier of the complexity dependent weight of unary nodes.
+    pub unary_weight: f32,
+    /// Multiplier of the complexity dependent weight of binary nodes.
+    pub binary_weight: f32,
+    /// Multiplier of the complexity dependent weight of fractal nodes.
+    pub fractal_weight: f32,
+    pub basis: BasisWeights,
+    pub unary: UnaryWeights,
+    pub binary: BinaryWeights,
+    /// Custom nodes.
+    pub nodes: NodeRegistry,
+    /// Maximum depth of the node tree, where the root is at depth 0.
+    /// Nodes at maximum depth are bases. Unlimited by default.
+    pub max_depth: usize,
+    /// Minimum and maximum basis frequency.
+    pub frequency: (f32, f32),
+    /// Minimum and maximum base frequency of fractals.
+    pub fractal_frequency: (f32, f32),
+    /// Minimum and maximum number of fractal octaves (minimum >= 1).
+    pub octaves: (u32, u32),
+    /// Maximum estimated cost of the texture, relative to gradient noise (see `Texture::estimated_cost`).
+    /// Trees are simplified to fit by choosing cheaper nodes and dropping fractal octaves.
+    /// Custom nodes count with their registered cost. The generators return `None`
+    /// for textures that are still over budget; `gen_within_budget` retries with fresh Dna.
+    /// Unlimited by default.
+    pub max_cost: f32,
+}
+
+impl Default for GenConfig {
+    fn default() -> Self {
+        Self {
+            basis_weight: 1.0,
+            unary_weight: 1.0,
+            binary_weight: 1.0,
+            fractal_weight: 1.0,
+            basis: BasisWeights::default(),
+            unary: UnaryWeights::default(),
+            binary: BinaryWeights::default(),
+            nodes: NodeRegistry::default(),
+            max_depth: usize::MAX,
+            frequency: (2.0, 32.0),
+            fractal_frequency: (1.5, 9.0),
+            octaves: (2, 10),
+            max_cost: f32::INFINITY,
+        }
+    }
+}
+
+impl GenConfig {
+    /// Check the invariants of the configuration. Panics if the configuration is invalid.
+    /// Called by the generators.
+    pub fn validate(&self) {
+        assert!(
+            1 <= self.octaves.0 && self.octaves.0 <= self.octaves.1,
+            "octaves must satisfy 1 <= minimum <= maximum"
+        );
+        for (minimum, maximum) in [self.frequency, self.fractal_frequency] {
+            assert!(
+                0.0 < minimum && minimum <= maximum,
+                "frequencies must satisfy 0 < minimum <= maximum"
+            );
+        }
+        assert!(!self.max_cost.is_nan(), "max_cost must not be NaN");
+        let basis = [
+            self.basis.gradient_noise,
+            self.basis.value_noise,
+            self.basis.voronoi,
+            self.basis.camo,
+            self.basis.gabor,
+            self.basis.stripes,
+            self.basis.rings,
+            self.basis.spherical_waves,
+            self.basis.spots,
+            self.basis.truchet,
+        ];
+        let unary = [
+            self.unary.saturate,
+            self.unary.posterize,
+            self.unary.overdrive,
+            self.unary.vreflect,
+            self.unary.reflect,
+            self.unary.shift,
+            self.unary.mirror,
+            self.unary.kaleidoscope,
+            self.unary.radial_repeat,
+            self.unary.coordinates,
+        ];
+        let binary = [
+            self.binary.rotate,
+            self.binary.softmix,
+            self.binary.layer,
+            self.binary.displace,
+        ];
+        let node_weights = [
+            self.basis_weight,
+            self.unary_weight,
+            self.binary_weight,
+            self.fractal_weight,
+        ];
+        let weights = basis
+            .iter()
+            .chain(unary.iter())
+            .chain(binary.iter())
+            .chain(node_weights.iter())
+            .copied()
+            .chain(self.nodes.basis.iter().map(|x| x.weight))
+            .chain(self.nodes.unary.iter().map(|x| x.weight))
+            .chain(self.nodes.binary.iter().map(|x| x.weight));
+        for weight in weights {
+            assert!(weight >= 0.0, "weights must be non-negative");
+        }
+        let costs = self
+            .nodes
+            .basis
+            .iter()
+            .map(|x| x.cost)
+            .chain(self.nodes.unary.iter().map(|x| x.cost))
+            .chain(self.nodes.binary.iter().map(|x| x.cost));
+        for cost in costs {
+            assert!(cost >= 0.0, "costs of custom nodes must be non-negative");
+        }
+        // Bases terminate the tree, so they must always be allowed.
+        assert!(self.basis_weight > 0.0, "basis_weight must be positive");
+        fn allows<F: ?Sized>(builtin: &[f32], custom: &[CustomNode<F>]) -> bool {
+            builtin.iter().any(|&weight| weight > 0.0) || custom.iter().any(|x| x.weight > 0.0)
+        }
+        assert!(
+            allows(&basis, &self.nodes.basis),
+            "some basis must be allowed"
+        );
+        assert!(
+            self.unary_weight == 0.0 || allows(&unary, &self.nodes.unary),
+            "some unary node must be allowed"
+        );
+        assert!(
+            self.binary_weight == 0.0 || allows(&binary, &self.nodes.binary),
+            "some binary node must be allowed"
+        );
+    }
+}
 
 /// Generate a distance metric.
 pub fn gen_metric(dna: &mut Dna, name: &str) -> Distance {
-    dna.choice(
+    dna.ordered_choice(
         name,
         [
             (1.0, "1-norm", Distance::Norm1),
@@ -27,6 +422,18 @@ pub fn gen_metric(dna: &mut Dna, name: &str) -> Distance {
     )
 }
 
+/// Generate a coordinate axis. The Z axis is favored as textures are usually viewed in XY slices.
+pub fn gen_axis(dna: &mut Dna, name: &str) -> Axis {
+    dna.choice(
+        name,
+        [
+            (1.0, "X", Axis::X),
+            (1.0, "Y", Axis::Y),
+            (2.0, "Z", Axis::Z),
+        ],
+    )
+}
+
 /// Generate an ease that is smooth near zero.
 pub fn gen_ease_smooth(dna: &mut Dna, name: &str) -> Ease {
     dna.choice(
@@ -79,12 +486,8 @@ pub fn gen_ease(dna: &mut Dna, name: &str) -> Ease {
 
 /// Generate a texture with a palette.
 pub fn genmap3palette(complexity: f32, tiling: TilingMode, dna: &mut Dna) -> Box<dyn Texture> {
-    match tiling {
-        TilingMode::None => genmap3palette_hasher(complexity, tile_none(), dna),
-        TilingMode::Z => genmap3palette_hasher(complexity, tile_z(), dna),
-        TilingMode::XY => genmap3palette_hasher(complexity, tile_xy(), dna),
-        TilingMode::All => genmap3palette_hasher(complexity, tile_all(), dna),
-    }
+    // The default configuration has no cost limit.
+    genmap3palette_config(complexity, tiling, &GenConfig::default(), dna).unwrap()
 }
 
 /// Generate a texture with a palette.
@@ -93,28 +496,60 @@ pub fn genmap3palette_hasher<H: 'static + Hasher>(
     hasher: H,
     dna: &mut Dna,
 ) -> Box<dyn Texture> {
+    genmap3palette_config_hasher(complexity, &GenConfig::default(), hasher, dna).unwrap()
+}
+
+/// Generate a texture with a palette using the configuration.
+/// Returns `None` if the texture exceeds the cost budget of the configuration.
+pub fn genmap3palette_config(
+    complexity: f32,
+    tiling: TilingMode,
+    config: &GenConfig,
+    dna: &mut Dna,
+) -> Option<Box<dyn Texture>> {
+    match tiling {
+        TilingMode::None => genmap3palette_config_hasher(complexity, config, tile_none(), dna),
+        TilingMode::Z => genmap3palette_config_hasher(complexity, config, tile_z(), dna),
+        TilingMode::XY => genmap3palette_config_hasher(complexity, config, tile_xy(), dna),
+        TilingMode::All => genmap3palette_config_hasher(complexity, config, tile_all(), dna),
+    }
+}
+
+/// Generate a texture with a palette using the configuration.
+/// Returns `None` if the texture exceeds the cost budget of the configuration.
+pub fn genmap3palette_config_hasher<H: 'static + Hasher>(
+    complexity: f32,
+    config: &GenConfig,
+    hasher: H,
+    dna: &mut Dna,
+) -> Option<Box<dyn Texture>> {
     let h1 = dna.f32("hue 1");
     let s1 = dna.f32("saturation 1");
-    let l1 = dna.f32_xform("lightness 1", sqrt);
+    let l1 = dna.f32_pow("lightness 1", 0.0, 1.0, 0.5);
     let h2 = dna.f32("hue 2");
     let s2 = dna.f32("saturation 2");
-    let l2 = dna.f32_xform("lightness 2", sqrt);
+    let l2 = dna.f32_pow("lightness 2", 0.0, 1.0, 0.5);
     let h3 = dna.f32("hue 3");
     let s3 = dna.f32("saturation 3");
-    let l3 = dna.f32_xform("lightness 3", sqrt);
-    let map = genmap3_hasher(complexity, false, hasher, dna);
+    let l3 = dna.f32_pow("lightness 3", 0.0, 1.0, 0.5);
+    config.validate();
+    let map = genmap3_node(
+        complexity,
+        false,
+        0,
+        config.max_cost - PALETTE_COST,
+        config,
+        hasher,
+        dna,
+    );
 
-    palette(h1, s1, l1, h2, s2, l2, h3, s3, l3, map)
+    within_budget(palette(h1, s1, l1, h2, s2, l2, h3, s3, l3, map), config)
 }
 
 /// Generate a texture.
 pub fn genmap3(complexity: f32, tiling: TilingMode, dna: &mut Dna) -> Box<dyn Texture> {
-    match tiling {
-        TilingMode::None => genmap3_hasher(complexity, false, tile_none(), dna),
-        TilingMode::Z => genmap3_hasher(complexity, false, tile_z(), dna),
-        TilingMode::XY => genmap3_hasher(complexity, false, tile_xy(), dna),
-        TilingMode::All => genmap3_hasher(complexity, false, tile_all(), dna),
-    }
+    // The default configuration has no cost limit.
+    genmap3_config(complexity, tiling, &GenConfig::default(), dna).unwrap()
 }
 
 /// Generate a texture.
@@ -123,6 +558,66 @@ pub fn genmap3_hasher<H: 'static + Hasher>(
     is_fractal: bool,
     hasher: H,
     dna: &mut Dna,
+) -> Box<dyn Texture> {
+    genmap3_config_hasher(complexity, is_fractal, &GenConfig::default(), hasher, dna).unwrap()
+}
+
+/// Generate a texture using the configuration.
+/// Returns `None` if the texture exceeds the cost budget of the configuration.
+pub fn genmap3_config(
+    complexity: f32,
+    tiling: TilingMode,
+    config: &GenConfig,
+    dna: &mut Dna,
+) -> Option<Box<dyn Texture>> {
+    match tiling {
+        TilingMode::None => genmap3_config_hasher(complexity, false, config, tile_none(), dna),
+        TilingMode::Z => genmap3_config_hasher(complexity, false, config, tile_z(), dna),
+        TilingMode::XY => genmap3_config_hasher(complexity, false, config, tile_xy(), dna),
+        TilingMode::All => genmap3_config_hasher(complexity, false, config, tile_all(), dna),
+    }
+}
+
+/// Generate a texture using the configuration.
+/// Returns `None` if the texture exceeds the cost budget of the configuration.
+pub fn genmap3_config_hasher<H: 'static + Hasher>(
+    complexity: f32,
+    is_fractal: bool,
+    config: &GenConfig,
+    hasher: H,
+    dna: &mut Dna,
+) -> Option<Box<dyn Texture>> {
+    config.validate();
+    let texture = genmap3_node(
+        complexity,
+        is_fractal,
+        0,
+        config.max_cost,
+        config,
+        hasher,
+        dna,
+    );
+    within_budget(texture, config)
+}
+
+/// Check the estimated cost of a generated texture against the budget of the configuration.
+fn within_budget(texture: Box<dyn Texture>, config: &GenConfig) -> Option<Box<dyn Texture>> {
+    if texture.estimated_cost() <= config.max_cost {
+        Some(texture)
+    } else {
+        None
+    }
+}
+
+/// Generate a node at the given depth of the texture tree.
+fn genmap3_node<H: 'static + Hasher>(
+    complexity: f32,
+    is_fractal: bool,
+    depth: usize,
+    budget: f32,
+    config: &GenConfig,
+    hasher: H,
+    dna: &mut Dna,
 ) -> Box<dyn Texture> {
     let basis_weight = if complexity <= 10.0 {
         1.5
@@ -151,13 +646,28 @@ pub fn genmap3_hasher<H: 'static + Hasher>(
         0.01
     };
 
+    // Only bases are allowed at maximum depth or when the budget is nearly spent.
+    let inner = if depth < config.max_depth && budget >= MIN_INNER_BUDGET {
+        1.0
+    } else {
+        0.0
+    };
+    let fractal_budget = if budget >= config.octaves.0 as f32 * (FRACTAL_COST + 1.0) {
+        1.0
+    } else {
+        0.0
+    };
+
     let choice = dna.index(
         "node type",
         [
-            (basis_weight, "basis"),
-            (unary_weight, "unary"),
-            (binary_weight, "binary"),
-            (fractal_weight, "fractal"),
+            (basis_weight * config.basis_weight, "basis"),
+            (unary_weight * config.unary_weight * inner, "unary"),
+            (binary_weight * config.binary_weight * inner, "binary"),
+            (
+                fractal_weight * config.fractal_weight * inner * fractal_budget,
+                "fractal",
+            ),
         ],
     );
 
@@ -169,17 +679,33 @@ pub fn genmap3_hasher<H: 'static + Hasher>(
             // The frequency comes from the fractalizer so we can choose any value.
             2.0
         } else {
-            dna.f32_xform("frequency", |x| xerp(2.0, 32.0, x))
+            dna.f32_xerp("frequency", config.frequency.0, config.frequency.1)
         };
-        let texture: Box<dyn Texture> = match dna.index(
-            "basis",
-            [
-                (1.0, "gradient noise"),
-                (1.0, "value noise"),
-                (1.0, "Voronoi"),
-                (0.5, "camo"),
-            ],
-        ) {
+        // Built-in bases are listed with their maximum estimated cost.
+        let bases = [
+            (config.basis.gradient_noise, "gradient noise", 1.0),
+            (config.basis.value_noise, "value noise", 0.75),
+            (config.basis.voronoi, "Voronoi", 6.3),
+            (config.basis.camo, "camo", 6.62),
+            (config.basis.gabor, "Gabor noise", 1.4),
+            (config.basis.stripes, "stripes", 0.1),
+            (config.basis.rings, "rings", 0.1),
+            (config.basis.spherical_waves, "spherical waves", 0.1),
+            (config.basis.spots, "spots", 2.5),
+            (config.basis.truchet, "Truchet", 0.3),
+        ];
+        let builtin = bases.len();
+        let mut choices: Vec<(f32, &str)> = bases
+            .iter()
+            .map(|&(weight, name, _)| (weight, name))
+            .collect();
+        choices.extend(config.nodes.basis.iter().map(|x| (x.weight, x.name())));
+        let costs = bases
+            .iter()
+            .map(|&(_, _, cost)| cost)
+            .chain(config.nodes.basis.iter().map(|x| x.cost));
+        affordable_choices(&mut choices, costs, budget);
+        let texture: Box<dyn Texture> = match dna.index_slice("basis", &choices) as usize {
             0 => noise(seed, frequency, hasher.clone()),
             1 => {
                 dna.group();
@@ -206,7 +732,7 @@ pub fn genmap3_hasher<H: 'static + Hasher>(
                     pattern_z as usize,
                 )
             }
-            _ => {
+            3 => {
                 dna.group();
                 let border = dna.generate(|dna| {
                     if dna.index("border", [(0.5, "on"), (0.5, "off")]) == 0 {
@@ -231,6 +757,103 @@ pub fn genmap3_hasher<H: 'static + Hasher>(
                     gradient,
                 )
             }
+            4 => {
+                dna.group();
+                let kernel_frequency = dna.f32_in("kernel frequency", 0.5, 3.0);
+                let bandwidth = dna.f32_in("bandwidth", 0.5, 2.0);
+                let direction = dna.generate(|dna| {
+                    if dna.index("orientation", [(0.5, "isotropic"), (0.5, "directional")]) == 1 {
+                        let x = dna.f32_in("X direction", -1.0, 1.0);
+                        let y = dna.f32_in("Y direction", -1.0, 1.0);
+                        let z = dna.f32_in("Z direction", -1.0, 1.0);
+                        Some(vec3(x, y, z))
+                    } else {
+                        None
+                    }
+                });
+                dna.ungroup();
+                match direction {
+                    Some(direction) if direction.length() > 1.0e-3 => gabor_oriented(
+                        seed,
+                        frequency,
+                        kernel_frequency,
+                        bandwidth,
+                        direction,
+                        hasher.clone(),
+                    ),
+                    _ => gabor(seed, frequency, kernel_frequency, bandwidth, hasher.clone()),
+                }
+            }
+            5 => {
+                dna.group();
+                let axis = gen_axis(dna, "axis");
+                let ease = gen_ease(dna, "stripe ease");
+                dna.ungroup();
+                stripes(seed, frequency, axis, ease, hasher.clone())
+            }
+            6 => {
+                dna.group();
+                let axis = gen_axis(dna, "axis");
+                let count = dna.f32_in("rings", 1.0, 8.0);
+                let ease = gen_ease(dna, "ring ease");
+                dna.ungroup();
+                rings(seed, frequency, axis, count, ease, hasher.clone())
+            }
+            7 => {
+                dna.group();
+                let count = dna.f32_in("waves", 1.0, 8.0);
+                let ease = gen_ease(dna, "wave ease");
+                dna.ungroup();
+                spherical_waves(seed, frequency, count, ease, hasher.clone())
+            }
+            8 => {
+                dna.group();
+                let density = dna.f32_in("density", 0.2, 2.0);
+                let min_radius = dna.f32_in("minimum radius", 0.05, 0.5);
+                let max_radius = dna.f32_in("maximum radius", 0.5, 1.0);
+                let stamp = dna.choice(
+                    "stamp",
+                    [
+                        (1.0, "crater", Stamp::Crater),
+                        (1.0, "dot", Stamp::Dot),
+                        (1.0, "ring", Stamp::Ring),
+                    ],
+                );
+                let ease = gen_ease_smooth(dna, "stamp ease");
+                dna.ungroup();
+                spots(
+                    seed,
+                    frequency,
+                    density,
+                    min_radius,
+                    max_radius,
+                    stamp,
+                    ease,
+                    hasher.clone(),
+                )
+            }
+            9 => {
+                dna.group();
+                let axis = gen_axis(dna, "axis");
+                let tile = dna.choice(
+                    "tile",
+                    [
+                        (1.0, "arcs", TruchetTile::Arcs),
+                        (1.0, "diagonals", TruchetTile::Diagonals),
+                    ],
+                );
+                let ease = gen_ease(dna, "tile ease");
+                dna.ungroup();
+                truchet(seed, frequency, axis, tile, ease, hasher.clone())
+            }
+            i => {
+                dna.group();
+                let node = &config.nodes.basis[i - builtin];
+                let texture =
+                    (node.constructor)(seed, frequency, AnyHasher::new(hasher.clone()), dna);
+                dna.ungroup();
+                texture
+            }
         };
         dna.ungroup();
         texture
@@ -238,22 +861,40 @@ pub fn genmap3_hasher<H: 'static + Hasher>(
         // Shape a map with a unary operator.
         dna.group();
         let child_complexity = complexity * 0.5 - 1.0;
-        let unary_node = match dna.index(
-            "unary node",
-            [
-                (1.0, "saturate"),
-                (1.0, "posterize"),
-                (1.0, "overdrive"),
-                (1.0, "vreflect"),
-                (2.0, "reflect"),
-                (3.0, "shift"),
-            ],
-        ) {
+        let child_budget = budget - UNARY_COST;
+        let mut choices = vec![
+            (config.unary.saturate, "saturate"),
+            (config.unary.posterize, "posterize"),
+            (config.unary.overdrive, "overdrive"),
+            (config.unary.vreflect, "vreflect"),
+            (config.unary.reflect, "reflect"),
+            (config.unary.shift, "shift"),
+            (config.unary.mirror, "mirror"),
+            (config.unary.kaleidoscope, "kaleidoscope"),
+            (config.unary.radial_repeat, "radial repeat"),
+            (config.unary.coordinates, "coordinates"),
+        ];
+        let builtin = choices.len();
+        choices.extend(config.nodes.unary.iter().map(|x| (x.weight, x.name())));
+        let costs = (0..builtin)
+            .map(|_| UNARY_COST)
+            .chain(config.nodes.unary.iter().map(|x| x.cost));
+        affordable_choices(&mut choices, costs, budget);
+        let unary_choice = dna.index_slice("unary node", &choices) as usize;
+        let unary_node = match unary_choice {
             0 => {
                 dna.group();
                 let amount = dna.f32_in("amount", 1.0, 5.0);
                 let child = dna.generate(|dna| {
-                    genmap3_hasher(child_complexity, is_fractal, hasher.clone(), dna)
+                    genmap3_node(
+                        child_complexity,
+                        is_fractal,
+                        depth + 1,
+                        child_budget,
+                        config,
+                        hasher.clone(),
+                        dna,
+                    )
                 });
                 dna.ungroup();
                 saturate(amount * amount, child)
@@ -263,7 +904,15 @@ pub fn genmap3_hasher<H: 'static + Hasher>(
                 let levels = dna.f32_in("levels", 2.0, 10.0);
                 let sharpness: f32 = dna.f32("sharpness");
                 let child = dna.generate(|dna| {
-                    genmap3_hasher(child_complexity, is_fractal, hasher.clone(), dna)
+                    genmap3_node(
+                        child_complexity,
+                        is_fractal,
+                        depth + 1,
+                        child_budget,
+                        config,
+                        hasher.clone(),
+                        dna,
+                    )
                 });
                 dna.ungroup();
                 posterize(levels, sharpness, child)
@@ -272,7 +921,15 @@ pub fn genmap3_hasher<H: 'static + Hasher>(
                 dna.group();
                 let amount = dna.f32_in("amount", 1.0, 5.0);
                 let child = dna.generate(|dna| {
-                    genmap3_hasher(child_complexity, is_fractal, hasher.clone(), dna)
+                    genmap3_node(
+                        child_complexity,
+                        is_fractal,
+                        depth + 1,
+                        child_budget,
+                        config,
+                        hasher.clone(),
+                        dna,
+                    )
                 });
                 dna.ungroup();
                 overdrive(amount * amount, child)
@@ -281,7 +938,15 @@ pub fn genmap3_hasher<H: 'static + Hasher>(
                 dna.group();
                 let amount = dna.f32_in("amount", 1.0, 10.0);
                 let child = dna.generate(|dna| {
-                    genmap3_hasher(child_complexity, is_fractal, hasher.clone(), dna)
+                    genmap3_node(
+                        child_complexity,
+                        is_fractal,
+                        depth + 1,
+                        child_budget,
+                        config,
+                        hasher.clone(),
+                        dna,
+                    )
                 });
                 dna.ungroup();
                 vreflect(amount, child)
@@ -293,20 +958,126 @@ pub fn genmap3_hasher<H: 'static + Hasher>(
                 let y_offset = dna.f32_in("Y offset", -1.0, 1.0);
                 let z_offset = dna.f32_in("Z offset", -1.0, 1.0);
                 let child = dna.generate(|dna| {
-                    genmap3_hasher(child_complexity, is_fractal, hasher.clone(), dna)
+                    genmap3_node(
+                        child_complexity,
+                        is_fractal,
+                        depth + 1,
+                        child_budget,
+                        config,
+                        hasher.clone(),
+                        dna,
+                    )
                 });
                 dna.ungroup();
                 reflect(amount, vec3(x_offset, y_offset, z_offset), child)
             }
-            _ => {
+            5 => {
                 dna.group();
                 let seed = dna.u32("seed");
                 let child = dna.generate(|dna| {
-                    genmap3_hasher(child_complexity, is_fractal, hasher.clone(), dna)
+                    genmap3_node(
+                        child_complexity,
+                        is_fractal,
+                        depth + 1,
+                        child_budget,
+                        config,
+                        hasher.clone(),
+                        dna,
+                    )
                 });
                 dna.ungroup();
                 shift(seed, child)
             }
+            6 => {
+                dna.group();
+                let (x, y, z) = dna.choice(
+                    "axes",
+                    [
+                        (1.0, "X", (true, false, false)),
+                        (1.0, "Y", (false, true, false)),
+                        (2.0, "XY", (true, true, false)),
+                        (0.5, "XYZ", (true, true, true)),
+                    ],
+                );
+                let child = dna.generate(|dna| {
+                    genmap3_node(
+                        child_complexity,
+                        is_fractal,
+                        depth + 1,
+                        child_budget,
+                        config,
+                        hasher.clone(),
+                        dna,
+                    )
+                });
+                dna.ungroup();
+                mirror(x, y, z, child)
+            }
+            7 | 8 => {
+                dna.group();
+                let segments = dna.u32_in("segments", 2, 12);
+                let axis = gen_axis(dna, "axis");
+                let child = dna.generate(|dna| {
+                    genmap3_node(
+                        child_complexity,
+                        is_fractal,
+                        depth + 1,
+                        child_budget,
+                        config,
+                        hasher.clone(),
+                        dna,
+                    )
+                });
+                dna.ungroup();
+                if unary_choice == 7 {
+                    kaleidoscope(segments, axis, child)
+                } else {
+                    radial_repeat(segments, axis, child)
+                }
+            }
+            9 => {
+                dna.group();
+                let system = dna.index(
+                    "system",
+                    [(1.0, "polar"), (1.0, "cylindrical"), (0.5, "spherical")],
+                );
+                let axis = gen_axis(dna, "axis");
+                let child = dna.generate(|dna| {
+                    genmap3_node(
+                        child_complexity,
+                        is_fractal,
+                        depth + 1,
+                        child_budget,
+                        config,
+                        hasher.clone(),
+                        dna,
+                    )
+                });
+                dna.ungroup();
+                match system {
+                    0 => to_polar(axis, child),
+                    1 => to_cylindrical(axis, child),
+                    _ => to_spherical(child),
+                }
+            }
+            i => {
+                dna.group();
+                let custom = &config.nodes.unary[i - builtin];
+                let child = dna.generate(|dna| {
+                    genmap3_node(
+                        child_complexity,
+                        is_fractal,
+                        depth + 1,
+                        budget - custom.cost,
+                        config,
+                        hasher.clone(),
+                        dna,
+                    )
+                });
+                let node = (custom.constructor)(child, dna);
+                dna.ungroup();
+                node
+            }
         };
         dna.ungroup();
         unary_node
@@ -314,23 +1085,45 @@ pub fn genmap3_hasher<H: 'static + Hasher>(
         // Combine two maps with a binary operator.
         dna.group();
         let child_complexity = complexity * 0.5 - 1.0;
-        let binary_node = match dna.index(
-            "binary node",
-            [
-                (1.0, "rotate"),
-                (1.0, "softmix"),
-                (1.0, "layer"),
-                (2.0, "displace"),
-            ],
-        ) {
+        // The second child gets whatever budget the first child leaves over.
+        let binary_budget = budget - BINARY_COST;
+        let mut choices = vec![
+            (config.binary.rotate, "rotate"),
+            (config.binary.softmix, "softmix"),
+            (config.binary.layer, "layer"),
+            (config.binary.displace, "displace"),
+        ];
+        let builtin = choices.len();
+        choices.extend(config.nodes.binary.iter().map(|x| (x.weight, x.name())));
+        let costs = (0..builtin)
+            .map(|_| BINARY_COST)
+            .chain(config.nodes.binary.iter().map(|x| x.cost));
+        affordable_choices(&mut choices, costs, budget);
+        let binary_node = match dna.index_slice("binary node", &choices) as usize {
             0 => {
                 dna.group();
                 let amount = dna.f32_in("amount", 1.0, 3.0);
                 let child_a = dna.generate(|dna| {
-                    genmap3_hasher(child_complexity, is_fractal, hasher.clone(), dna)
+                    genmap3_node(
+                        child_complexity,
+                        is_fractal,
+                        depth + 1,
+                        binary_budget * 0.5,
+                        config,
+                        hasher.clone(),
+                        dna,
+                    )
                 });
                 let child_b = dna.generate(|dna| {
-                    genmap3_hasher(child_complexity, is_fractal, hasher.clone(), dna)
+                    genmap3_node(
+                        child_complexity,
+                        is_fractal,
+                        depth + 1,
+                        binary_budget - child_a.estimated_cost(),
+                        config,
+                        hasher.clone(),
+                        dna,
+                    )
                 });
                 dna.ungroup();
                 rotate(amount, child_a, child_b)
@@ -340,10 +1133,26 @@ pub fn genmap3_hasher<H: 'static + Hasher>(
                 let amount = dna.f32_in("amount", 1.0, 5.0);
                 let displacement = dna.f32_in("displacement", 0.0, 0.5);
                 let child_a = dna.generate(|dna| {
-                    genmap3_hasher(child_complexity, is_fractal, hasher.clone(), dna)
+                    genmap3_node(
+                        child_complexity,
+                        is_fractal,
+                        depth + 1,
+                        binary_budget * 0.5,
+                        config,
+                        hasher.clone(),
+                        dna,
+                    )
                 });
                 let child_b = dna.generate(|dna| {
-                    genmap3_hasher(child_complexity, is_fractal, hasher.clone(), dna)
+                    genmap3_node(
+                        child_complexity,
+                        is_fractal,
+                        depth + 1,
+                        binary_budget - child_a.estimated_cost(),
+                        config,
+                        hasher.clone(),
+                        dna,
+                    )
                 });
                 dna.ungroup();
                 softmix3(amount * amount, displacement, child_a, child_b)
@@ -353,26 +1162,88 @@ pub fn genmap3_hasher<H: 'static + Hasher>(
                 let width = dna.f32_in("width", 1.0, 3.0);
                 let ease = gen_ease(dna, "layer ease");
                 let child_a = dna.generate(|dna| {
-                    genmap3_hasher(child_complexity, is_fractal, hasher.clone(), dna)
+                    genmap3_node(
+                        child_complexity,
+                        is_fractal,
+                        depth + 1,
+                        binary_budget * 0.5,
+                        config,
+                        hasher.clone(),
+                        dna,
+                    )
                 });
                 let child_b = dna.generate(|dna| {
-                    genmap3_hasher(child_complexity, is_fractal, hasher.clone(), dna)
+                    genmap3_node(
+                        child_complexity,
+                        is_fractal,
+                        depth + 1,
+                        binary_budget - child_a.estimated_cost(),
+                        config,
+                        hasher.clone(),
+                        dna,
+                    )
                 });
                 dna.ungroup();
                 layer(width, ease, child_a, child_b)
             }
-            _ => {
+            3 => {
                 dna.group();
                 let amount = dna.f32_in("amount", 0.0, 0.5);
                 let child_a = dna.generate(|dna| {
-                    genmap3_hasher(child_complexity, is_fractal, hasher.clone(), dna)
+                    genmap3_node(
+                        child_complexity,
+                        is_fractal,
+                        depth + 1,
+                        binary_budget * 0.5,
+                        config,
+                        hasher.clone(),
+                        dna,
+                    )
                 });
                 let child_b = dna.generate(|dna| {
-                    genmap3_hasher(child_complexity, is_fractal, hasher.clone(), dna)
+                    genmap3_node(
+                        child_complexity,
+                        is_fractal,
+                        depth + 1,
+                        binary_budget - child_a.estimated_cost(),
+                        config,
+                        hasher.clone(),
+                        dna,
+                    )
                 });
                 dna.ungroup();
                 displace(amount, child_a, child_b)
             }
+            i => {
+                dna.group();
+                let custom = &config.nodes.binary[i - builtin];
+                let binary_budget = budget - custom.cost;
+                let child_a = dna.generate(|dna| {
+                    genmap3_node(
+                        child_complexity,
+                        is_fractal,
+                        depth + 1,
+                        binary_budget * 0.5,
+                        config,
+                        hasher.clone(),
+                        dna,
+                    )
+                });
+                let child_b = dna.generate(|dna| {
+                    genmap3_node(
+                        child_complexity,
+                        is_fractal,
+                        depth + 1,
+                        binary_budget - child_a.estimated_cost(),
+                        config,
+                        hasher.clone(),
+                        dna,
+                    )
+                });
+                let node = (custom.constructor)(child_a, child_b, dna);
+                dna.ungroup();
+                node
+            }
         };
         dna.ungroup();
         binary_node
@@ -380,11 +1251,15 @@ pub fn genmap3_hasher<H: 'static + Hasher>(
         // Fractalize map by sampling many octaves.
         dna.group();
         let child_complexity = min(20.0, complexity * 0.5 - 1.0);
-        let base_f = dna.f32_in("base frequency", 1.5, 9.0);
-        let roughness = dna.f32_xform("roughness", |x| xerp(0.4, 0.9, x));
-        let octaves = dna.u32_in("octaves", 2, 10) as usize;
+        let base_f = dna.f32_in(
+            "base frequency",
+            config.fractal_frequency.0,
+            config.fractal_frequency.1,
+        );
+        let roughness = dna.f32_xerp("roughness", 0.4, 0.9);
+        let octaves = dna.u32_in("octaves", config.octaves.0, config.octaves.1) as usize;
         let first_octave = dna.u32_in("first octave", 0, octaves as u32 - 1) as usize;
-        let lacunarity = dna.f32_xform("lacunarity", |x| xerp(1.5, 3.0, x));
+        let lacunarity = dna.f32_xerp("lacunarity", 1.5, 3.0);
         let displace = dna.generate(|dna| {
             if dna.index("displace", [(0.333, "on"), (0.666, "off")]) == 0 {
                 dna.f32_in("amount", 0.0, 0.5)
@@ -399,9 +1274,24 @@ pub fn genmap3_hasher<H: 'static + Hasher>(
                 0.0
             }
         });
-        let child_basis =
-            dna.generate(|dna| genmap3_hasher(child_complexity, true, hasher.clone(), dna));
+        let child_basis = dna.generate(|dna| {
+            genmap3_node(
+                child_complexity,
+                true,
+                depth + 1,
+                budget / config.octaves.0 as f32 - FRACTAL_COST,
+                config,
+                hasher.clone(),
+                dna,
+            )
+        });
         dna.ungroup();
+        // Drop octaves that do not fit in the budget.
+        let octaves = min(
+            octaves,
+            max(1.0, budget / (FRACTAL_COST + child_basis.estimated_cost())) as usize,
+        );
+        let first_octave = min(first_octave, octaves - 1);
         fractal(
             base_f,
             octaves,
@@ -414,3 +1304,775 @@ pub fn genmap3_hasher<H: 'static + Hasher>(
         )
     }
 }
+
+/// Disallow choices that may exceed the budget, unless nothing else is allowed.
+/// `costs` are the maximum estimated costs of the choices, not including children.
+fn affordable_choices(choices: &mut [(f32, &str)], costs: impl Iterator<Item = f32>, budget: f32) {
+    let affordable: Vec<f32> = choices
+        .iter()
+        .zip(costs)
+        .map(|(&(weight, _), cost)| if cost <= budget { weight } else { 0.0 })
+        .collect();
+    if affordable.iter().any(|&weight| weight > 0.0) {
+        for (choice, weight) in choices.iter_mut().zip(affordable) {
+            choice.0 = weight;
+        }
+    }
+}
+
+/// Generate textures from fresh Dna until one has an estimated cost of at most `max_cost`,
+/// making up to `attempts` attempts. `generator` generates a texture from Dna,
+/// for example, `|dna| genmap3palette(20.0, TilingMode::All, dna)`.
+/// Dna seeds are derived from `seed`, so the result is deterministic.
+/// Returns the Dna and texture, or `None` if every attempt was over budget.
+pub fn gen_within_budget<G>(
+    max_cost: f32,
+    seed: u64,
+    attempts: usize,
+    mut generator: G,
+) -> Option<(Dna, Box<dyn Texture>)>
+where
+    G: FnMut(&mut Dna) -> Box<dyn Texture>,
+{
+    let mut rnd = Rnd::from_u64(seed);
+    for _ in 0..attempts {
+        let mut dna = Dna::new(rnd.u64());
+        let texture = generator(&mut dna);
+        if texture.estimated_cost() <= max_cost {
+            return Some((dna, texture));
+        }
+    }
+    None
+}
+
+/// Material presets. Each preset constrains structure and palette so that
+/// every genome is recognizably that material.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Material {
+    Marble,
+    Wood,
+    Stone,
+    Clouds,
+    Lava,
+    Camouflage,
+}
+
+impl Material {
+    /// All materials.
+    pub const ALL: [Material; 6] = [
+        Material::Marble,
+        Material::Wood,
+        Material::Stone,
+        Material::Clouds,
+        Material::Lava,
+        Material::Camouflage,
+    ];
+
+    /// Name of the generator function, for recording in Dna file headers.
+    pub fn generator_name(&self) -> &'static str {
+        match self {
+            Material::Marble => "gen_marble",
+            Material::Wood => "gen_wood",
+            Material::Stone => "gen_stone",
+            Material::Clouds => "gen_clouds",
+            Material::Lava => "gen_lava",
+            Material::Camouflage => "gen_camouflage",
+        }
+    }
+}
+
+/// Generate a material.
+pub fn gen_material(material: Material, tiling: TilingMode, dna: &mut Dna) -> Box<dyn Texture> {
+    match tiling {
+        TilingMode::None => gen_material_hasher(material, tile_none(), dna),
+        TilingMode::Z => gen_material_hasher(material, tile_z(), dna),
+        TilingMode::XY => gen_material_hasher(material, tile_xy(), dna),
+        TilingMode::All => gen_material_hasher(material, tile_all(), dna),
+    }
+}
+
+/// Generate a material.
+pub fn gen_material_hasher<H: 'static + Hasher>(
+    material: Material,
+    hasher: H,
+    dna: &mut Dna,
+) -> Box<dyn Texture> {
+    match material {
+        Material::Marble => gen_marble_hasher(hasher, dna),
+        Material::Wood => gen_wood_hasher(hasher, dna),
+        Material::Stone => gen_stone_hasher(hasher, dna),
+        Material::Clouds => gen_clouds_hasher(hasher, dna),
+        Material::Lava => gen_lava_hasher(hasher, dna),
+        Material::Camouflage => gen_camouflage_hasher(hasher, dna),
+    }
+}
+
+/// Palette from a base hue and three colors in Okhsl, where the first component of each color
+/// is a hue shift. Hues are offset by half a turn to compensate for `palette`.
+fn material_palette(
+    hue: f32,
+    c1: Vec3,
+    c2: Vec3,
+    c3: Vec3,
+    texture: Box<dyn Texture>,
+) -> Box<dyn Texture> {
+    let hue = |shift: f32| {
+        let h = hue + shift + 0.5;
+        h - floor(h)
+    };
+    palette(
+        hue(c1.x),
+        c1.y,
+        c1.z,
+        hue(c2.x),
+        c2.y,
+        c2.z,
+        hue(c3.x),
+        c3.y,
+        c3.z,
+        texture,
+    )
+}
+
+/// Fractal gradient noise for turbulence. Call inside `Dna::generate`.
+fn gen_turbulence<H: 'static + Hasher>(
+    dna: &mut Dna,
+    seed: u64,
+    octaves: (u32, u32),
+    hasher: H,
+) -> Box<dyn Texture> {
+    let base_f = dna.f32_xerp("base frequency", 1.5, 3.0);
+    let octaves = dna.u32_in("octaves", octaves.0, octaves.1) as usize;
+    let roughness = dna.f32_in("roughness", 0.4, 0.6);
+    let lacunarity = dna.f32_in("lacunarity", 1.8, 2.2);
+    fractal(
+        base_f,
+        octaves,
+        0,
+        roughness,
+        lacunarity,
+        0.0,
+        0.0,
+        noise_basis(seed, hasher),
+    )
+}
+
+/// Generate marble.
+pub fn gen_marble(tiling: TilingMode, dna: &mut Dna) -> Box<dyn Texture> {
+    gen_material(Material::Marble, tiling, dna)
+}
+
+/// Generate marble: stone with veins distorted by turbulence.
+pub fn gen_marble_hasher<H: 'static + Hasher>(hasher: H, dna: &mut Dna) -> Box<dyn Texture> {
+    let hue = dna.f32("hue");
+    let c1 = dna.vec3_in(
+        "stone color",
+        vec3(-0.05, 0.0, 0.75),
+        vec3(0.05, 0.15, 0.95),
+    );
+    let c2 = dna.vec3_in("middle color", vec3(-0.05, 0.0, 0.5), vec3(0.05, 0.3, 0.8));
+    let c3 = dna.vec3_in("vein color", vec3(-0.05, 0.0, 0.05), vec3(0.05, 0.5, 0.45));
+    let seed = dna.u32("seed") as u64;
+    let frequency = dna.f32_xerp("vein frequency", 1.0, 3.0);
+    let axis = gen_axis(dna, "axis");
+    let amount = dna.f32_in("turbulence", 0.3, 1.5);
+    let width = dna.f32_xerp("vein width", 0.1, 0.4);
+    let bias = dna.f32_in("vein bias", -1.0, -0.5);
+    let turbulence = dna.generate(|dna| gen_turbulence(dna, seed + 1, (4, 7), hasher.clone()));
+    // Veins follow zero crossings of the stripes.
+    let veins = reflect(
+        1.0 / width,
+        Vec3::ONE,
+        displace(
+            amount,
+            turbulence,
+            stripes(seed, frequency, axis, Ease::Smooth3, hasher),
+        ),
+    );
+    material_palette(hue, c1, c2, c3, ramp(bias, 1.0, veins))
+}
+
+/// Generate wood.
+pub fn gen_wood(tiling: TilingMode, dna: &mut Dna) -> Box<dyn Texture> {
+    gen_material(Material::Wood, tiling, dna)
+}
+
+/// Generate wood: growth rings around an axis with grain.
+pub fn gen_wood_hasher<H: 'static + Hasher>(hasher: H, dna: &mut Dna) -> Box<dyn Texture> {
+    let hue = dna.f32_in("hue", 0.06, 0.16);
+    let c1 = dna.vec3_in(
+        "early wood color",
+        vec3(-0.03, 0.3, 0.6),
+        vec3(0.03, 0.65, 0.85),
+    );
+    let c2 = dna.vec3_in(
+        "middle color",
+        vec3(-0.03, 0.35, 0.45),
+        vec3(0.03, 0.7, 0.7),
+    );
+    let c3 = dna.vec3_in(
+        "late wood color",
+        vec3(-0.03, 0.4, 0.2),
+        vec3(0.03, 0.75, 0.5),
+    );
+    let seed = dna.u32("seed") as u64;
+    let frequency = dna.f32_xerp("log frequency", 0.5, 1.5);
+    let axis = gen_axis(dna, "axis");
+    let count = dna.f32_in("rings", 3.0, 8.0);
+    let amount = dna.f32_in("grain", 0.05, 0.2);
+    let contrast = dna.f32_xerp("ring contrast", 1.0, 3.0);
+    let turbulence = dna.generate(|dna| gen_turbulence(dna, seed + 1, (3, 6), hasher.clone()));
+    let rings = displace(
+        amount,
+        turbulence,
+        rings(seed, frequency, axis, count, Ease::Smooth3, hasher),
+    );
+    material_palette(hue, c1, c2, c3, ramp(0.0, contrast, rings))
+}
+
+/// Generate stone.
+pub fn gen_stone(tiling: TilingMode, dna: &mut Dna) -> Box<dyn Texture> {
+    gen_material(Material::Stone, tiling, dna)
+}
+
+/// Generate stone: irregular cobbles with rough surfaces.
+pub fn gen_stone_hasher<H: 'static + Hasher>(hasher: H, dna: &mut Dna) -> Box<dyn Texture> {
+    let hue = dna.f32("hue");
+    let c1 = dna.vec3_in("dark color", vec3(-0.05, 0.0, 0.15), vec3(0.05, 0.15, 0.35));
+    let c2 = dna.vec3_in("middle color", vec3(-0.05, 0.0, 0.35), vec3(0.05, 0.2, 0.6));
+    let c3 = dna.vec3_in(
+        "light color",
+        vec3(-0.05, 0.0, 0.55),
+        vec3(0.05, 0.15, 0.85),
+    );
+    let seed = dna.u32("seed") as u64;
+    let frequency = dna.f32_xerp("frequency", 2.0, 8.0);
+    let border = dna.f32_in("mortar width", 0.02, 0.15);
+    let sharpness = dna.f32_in("sharpness", 0.6, 1.0);
+    let gradient = dna.f32_in("gradient", 0.0, 0.5);
+    let metric = gen_metric(dna, "distance metric");
+    let amount = dna.f32_in("distortion", 0.1, 0.5);
+    let roughness = dna.f32_in("surface roughness", 0.1, 0.5);
+    let turbulence = dna.generate(|dna| gen_turbulence(dna, seed + 1, (5, 8), hasher.clone()));
+    let cells = displace(
+        amount,
+        turbulence.clone(),
+        camo(
+            seed,
+            frequency,
+            Ease::Smooth3,
+            metric,
+            hasher,
+            border,
+            sharpness,
+            gradient,
+        ),
+    );
+    let surface = softmix3(1.0, 0.0, cells, turbulence);
+    material_palette(hue, c1, c2, c3, ramp(0.0, 1.0 + roughness, surface))
+}
+
+/// Generate clouds.
+pub fn gen_clouds(tiling: TilingMode, dna: &mut Dna) -> Box<dyn Texture> {
+    gen_material(Material::Clouds, tiling, dna)
+}
+
+/// Generate clouds: billowing turbulence over a sky.
+pub fn gen_clouds_hasher<H: 'static + Hasher>(hasher: H, dna: &mut Dna) -> Box<dyn Texture> {
+    let hue = dna.f32_in("hue", 0.6, 0.75);
+    let c1 = dna.vec3_in("sky color", vec3(-0.03, 0.5, 0.5), vec3(0.03, 0.9, 0.7));
+    let c2 = dna.vec3_in("haze color", vec3(-0.03, 0.1, 0.75), vec3(0.03, 0.4, 0.9));
+    let c3 = dna.vec3_in("cloud color", vec3(-0.03, 0.0, 0.95), vec3(0.03, 0.1, 1.0));
+    let seed = dna.u32("seed") as u64;
+    let coverage = dna.f32_in("coverage", -0.5, 0.5);
+    let contrast = dna.f32_xerp("contrast", 1.0, 3.0);
+    let amount = dna.f32_in("billow", 0.0, 0.5);
+    let base = dna.generate(|dna| gen_turbulence(dna, seed, (5, 8), hasher.clone()));
+    let turbulence = dna.generate(|dna| gen_turbulence(dna, seed + 1, (2, 4), hasher.clone()));
+    let clouds = displace(amount, turbulence, base);
+    material_palette(hue, c1, c2, c3, ramp(coverage, contrast, clouds))
+}
+
+/// Generate lava.
+pub fn gen_lava(tiling: TilingMode, dna: &mut Dna) -> Box<dyn Texture> {
+    gen_material(Material::Lava, tiling, dna)
+}
+
+/// Generate lava: dark crust broken by glowing cracks.
+pub fn gen_lava_hasher<H: 'static + Hasher>(hasher: H, dna: &mut Dna) -> Box<dyn Texture> {
+    let hue = dna.f32_in("hue", 0.05, 0.1);
+    let c1 = dna.vec3_in("crust color", vec3(-0.05, 0.0, 0.02), vec3(0.05, 0.3, 0.12));
+    let c2 = dna.vec3_in("glow color", vec3(-0.02, 0.85, 0.4), vec3(0.02, 1.0, 0.55));
+    let c3 = dna.vec3_in("hot color", vec3(0.08, 0.9, 0.7), vec3(0.15, 1.0, 0.9));
+    let seed = dna.u32("seed") as u64;
+    let amount = dna.f32_in("flow", 0.2, 1.0);
+    let width = dna.f32_xerp("crack width", 0.1, 0.3);
+    let heat = dna.f32_in("heat", -1.2, -0.6);
+    let base = dna.generate(|dna| gen_turbulence(dna, seed, (4, 7), hasher.clone()));
+    let turbulence = dna.generate(|dna| gen_turbulence(dna, seed + 1, (3, 5), hasher.clone()));
+    // Cracks follow zero crossings of the flow.
+    let cracks = reflect(1.0 / width, Vec3::ONE, displace(amount, turbulence, base));
+    material_palette(hue, c1, c2, c3, ramp(heat, 2.0, cracks))
+}
+
+/// Generate camouflage.
+pub fn gen_camouflage(tiling: TilingMode, dna: &mut Dna) -> Box<dyn Texture> {
+    gen_material(Material::Camouflage, tiling, dna)
+}
+
+/// Generate camouflage: blotches of muted natural colors.
+pub fn gen_camouflage_hasher<H: 'static + Hasher>(hasher: H, dna: &mut Dna) -> Box<dyn Texture> {
+    let hue = dna.f32_in("hue", 0.15, 0.3);
+    let c1 = dna.vec3_in("color 1", vec3(0.05, 0.2, 0.25), vec3(0.15, 0.45, 0.45));
+    let c2 = dna.vec3_in("color 2", vec3(-0.15, 0.2, 0.2), vec3(-0.05, 0.45, 0.4));
+    let c3 = dna.vec3_in("color 3", vec3(-0.05, 0.1, 0.45), vec3(0.05, 0.3, 0.65));
+    let seed = dna.u32("seed") as u64;
+    let frequency = dna.f32_xerp("frequency", 2.0, 6.0);
+    let amount = dna.f32_in("distortion", 0.5, 1.5);
+    let sharpness = dna.f32_in("sharpness", 0.7, 1.0);
+    let turbulence = dna.generate(|dna| gen_turbulence(dna, seed + 1, (3, 6), hasher.clone()));
+    let blotches = displace(
+        amount,
+        turbulence,
+        camo(
+            seed,
+            frequency,
+            Ease::Smooth3,
+            Distance::Norm2,
+            hasher,
+            0.0,
+            sharpness,
+            0.0,
+        ),
+    );
+    material_palette(hue, c1, c2, c3, saturate(8.0, blotches))
+}
+
+/// Distance between height samples in normal maps of `MaterialTextures`.
+const NORMAL_DELTA: f32 = 1.0 / 1024.0;
+
+/// Matched set of physically based rendering maps generated from one Dna.
+/// All maps are derived from the same structure texture, so features line up across maps.
+/// Values are in -1...1 like in other textures. Scalar maps repeat the value in all components.
+#[derive(Clone)]
+pub struct MaterialTextures {
+    /// Base color.
+    pub albedo: Box<dyn Texture>,
+    /// Height (scalar).
+    pub height: Box<dyn Texture>,
+    /// Tangent space unit normal derived from height.
+    pub normal: Box<dyn Texture>,
+    /// Roughness (scalar).
+    pub roughness: Box<dyn Texture>,
+    /// Metallic (scalar).
+    pub metallic: Box<dyn Texture>,
+    /// Ambient occlusion derived from height (scalar), where -1 is fully occluded.
+    pub occlusion: Box<dyn Texture>,
+}
+
+impl MaterialTextures {
+    /// Get code for instantiating the maps.
+    pub fn get_code(&self) -> String {
+        format!(
+            "MaterialTextures {{\n    albedo: {},\n    height: {},\n    normal: {},\n    roughness: {},\n    metallic: {},\n    occlusion: {},\n}}",
+            self.albedo.get_code(),
+            self.height.get_code(),
+            self.normal.get_code(),
+            self.roughness.get_code(),
+            self.metallic.get_code(),
+            self.occlusion.get_code()
+        )
+    }
+}
+
+/// Generate a matched set of material maps.
+pub fn gen_pbr(complexity: f32, tiling: TilingMode, dna: &mut Dna) -> MaterialTextures {
+    // The default configuration has no cost limit.
+    gen_pbr_config(complexity, tiling, &GenConfig::default(), dna).unwrap()
+}
+
+/// Generate a matched set of material maps.
+pub fn gen_pbr_hasher<H: 'static + Hasher>(
+    complexity: f32,
+    hasher: H,
+    dna: &mut Dna,
+) -> MaterialTextures {
+    gen_pbr_config_hasher(complexity, &GenConfig::default(), hasher, dna).unwrap()
+}
+
+/// Generate a matched set of material maps using the configuration.
+/// Returns `None` if the structure texture exceeds the cost budget of the configuration.
+pub fn gen_pbr_config(
+    complexity: f32,
+    tiling: TilingMode,
+    config: &GenConfig,
+    dna: &mut Dna,
+) -> Option<MaterialTextures> {
+    match tiling {
+        TilingMode::None => gen_pbr_config_hasher(complexity, config, tile_none(), dna),
+        TilingMode::Z => gen_pbr_config_hasher(complexity, config, tile_z(), dna),
+        TilingMode::XY => gen_pbr_config_hasher(complexity, config, tile_xy(), dna),
+        TilingMode::All => gen_pbr_config_hasher(complexity, config, tile_all(), dna),
+    }
+}
+
+/// Generate a matched set of material maps using the configuration.
+/// The structure texture is generated as in `genmap3`; each map mixes its components differently.
+/// The cost budget of the configuration applies to the structure texture;
+/// returns `None` if the structure texture exceeds it.
+pub fn gen_pbr_config_hasher<H: 'static + Hasher>(
+    complexity: f32,
+    config: &GenConfig,
+    hasher: H,
+    dna: &mut Dna,
+) -> Option<MaterialTextures> {
+    let c1 = dna.color("color 1");
+    let c2 = dna.color("color 2");
+    let c3 = dna.color("color 3");
+    let structure =
+        dna.generate(|dna| genmap3_config_hasher(complexity, false, config, hasher.clone(), dna))?;
+
+    let height_weights = dna.vec3_in("height weights", -Vec3::ONE, Vec3::ONE);
+    let height_weights = height_weights.try_normalize().unwrap_or(Vec3::X);
+    let height = mono(height_weights, 0.0, structure.clone());
+    let bump = dna.f32_xerp("bump", 0.002, 0.02);
+    let normal = normal_map(bump, NORMAL_DELTA, height.clone());
+
+    let roughness_weights = dna.vec3_in("roughness weights", -Vec3::ONE * 0.5, Vec3::ONE * 0.5);
+    let roughness_bias = dna.f32_in("roughness bias", -0.6, 0.8);
+    let roughness = mono(roughness_weights, roughness_bias, structure.clone());
+
+    let (metallic_weights, metallic_bias) = dna.generate(|dna| {
+        match dna.index("metal", [(0.6, "none"), (0.2, "partial"), (0.2, "full")]) {
+            0 => (Vec3::ZERO, -1.0),
+            1 => (
+                dna.vec3_in("metal weights", -Vec3::ONE * 4.0, Vec3::ONE * 4.0),
+                dna.f32_in("metal bias", -1.0, 1.0),
+            ),
+            _ => (Vec3::ZERO, 1.0),
+        }
+    });
+    let metallic = mono(metallic_weights, metallic_bias, structure.clone());
+
+    let radius = dna.f32_xerp("occlusion radius", 0.005, 0.05);
+    let strength = dna.f32_in("occlusion strength", 0.5, 4.0);
+    let occlusion = occlusion(radius, strength, height.clone());
+
+    // Favor lighter colors.
+    let albedo = palette(
+        c1.x,
+        c1.y,
+        sqrt(c1.z),
+        c2.x,
+        c2.y,
+        sqrt(c2.z),
+        c3.x,
+        c3.y,
+        sqrt(c3.z),
+        structure,
+    );
+
+    Some(MaterialTextures {
+        albedo,
+        height,
+        normal,
+        roughness,
+        metallic,
+        occlusion,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Codes of `genmap3palette(10.0, TilingMode::All, ..)` generated by funutd 0.16.0.
+    const PALETTE_CODES: [&str; 4] = [
+        "palette(0.8725408, 0.22480702, 0.79159933, 0.8980727, 0.24355304, 0.98904586, 0.25250968, 0.47551584, 0.9924903, softmix3(6.2371807, 0.332601, noise(396416421, 23.91766, tile_all()), vnoise(3189217754, 3.9145036, Ease::Smooth9, tile_all())))",
+        "palette(0.9498667, 0.036729805, 0.6219003, 0.7580778, 0.7092516, 0.9509995, 0.41297245, 0.91391456, 0.9176226, posterize(5.757073, 0.25448227, camo(239334775, 2.4249606, Ease::Cubed, Distance::Norm8, tile_all(), 0.0, 0.95693594, 0.15859318)))",
+        "palette(0.75899065, 0.26872972, 0.29185182, 0.2401845, 0.98054457, 0.8876085, 0.39039198, 0.5104629, 0.7780969, vnoise(3816583460, 2.7336674, Ease::Smooth5, tile_all()))",
+        "palette(0.8933311, 0.48830998, 0.8662937, 0.24635336, 0.617804, 0.13620299, 0.63700354, 0.7348224, 0.4501898, softmix3(7.987843, 0.082957506, camo(1267943923, 3.1592093, Ease::UpArc, Distance::Norm2, tile_all(), 0.0, 0.58507454, 0.8766245), vnoise(816184175, 6.737277, Ease::Smooth9, tile_all())))",
+    ];
+
+    /// Codes of `genmap3(10.0, TilingMode::None, ..)` generated by funutd 0.16.0.
+    const MAP_CODES: [&str; 4] = [
+        "fractal(3.1860528, 9, 5, 0.66488713, 2.9550328, 0.23775792, 0.0, vnoise_basis(859958775, Ease::Squared, tile_none()))",
+        "fractal(1.7754736, 10, 2, 0.5473563, 2.807649, 0.0, 0.0, noise_basis(1898071257, tile_none()))",
+        "softmix3(1.7975031, 0.12009225, shift(1676720745, camo(2600322783, 3.4742856, Ease::Smooth7, Distance::Norm1, tile_none(), 0.059227843, 0.29048172, 0.44537196)), noise(3568109146, 2.0987742, tile_none()))",
+        "fractal(5.162325, 10, 6, 0.735124, 1.5194128, 0.0, 0.0, vnoise_basis(2922399320, Ease::Smooth7, tile_none()))",
+    ];
+
+    /// Dna files saved by the editor of funutd 0.16.0, with legacy hashes, and their textures
+    /// generated by `genmap3palette(100.0, TilingMode::All, ..)`.
+    const LEGACY_FILES: [(&str, &str); 2] = [
+        (
+            "TilingMode All\n\
+             1813130097231483333 855467647\n\
+             13757936517878972693 1940533620\n\
+             9664192370030663506 1154185313\n\
+             14592700051037784884 74675499\n\
+             6813837123649185041 4211406726\n\
+             7137348655460554997 2349244090\n\
+             17586262991335053074 2192421446\n\
+             15041029425019963368 1247609506\n\
+             412725459992000564 1603127192\n\
+             13395536627341950202 3816583460\n\
+             10436354325127461806 1031584581\n\
+             4512591355308529199 3259840057\n\
+             5492541580822658387 1912857964\n\
+             7993526259568026707 73238828\n\
+             11385630495222641131 1920657427\n\
+             18284631340248880317 3568109146\n\
+             13170890303279092797 1676720745\n\
+             13082751936608761774 975147401\n\
+             15801484744529569551 4173905025\n\
+             11351417174732519145 3383785301\n\
+             7613461257930608790 3222674444\n\
+             14694436210920222197 2600322783\n\
+             14163607037637854427 186074561\n\
+             324734820672991787 510484153\n\
+             1640636169497594061 365834487\n\
+             12434548824485558541 484083348\n\
+             4219323793096549028 3765303790\n",
+            "palette(0.75899065, 0.26872972, 0.29185182, 0.2401845, 0.98054457, 0.8876085, 0.39039198, 0.5104629, 0.7780969, shift(484083348, softmix3(7.7366753, 0.22590785, noise(1920657427, 20.015705, tile_all()), shift(1603127192, noise(3222674444, 22.732943, tile_all())))))",
+        ),
+        (
+            "TilingMode All\n\
+             4512591355308529199 3764181513\n\
+             1813130097231483333 75677562\n\
+             9664192370030663506 1775818743\n\
+             4870747264636046721 3997385464\n\
+             6813837123649185041 1668435915\n\
+             13170890303279092797 2505938427\n\
+             17586262991335053074 433617286\n\
+             847251254708581015 1510712992\n\
+             11351417174732519145 146616617\n\
+             14694436210920222197 1572651603\n\
+             10743543381240005806 828263325\n\
+             1640636169497594061 1750638514\n\
+             10436354325127461806 3918961471\n",
+            "palette(0.8764168, 0.41346502, 0.6384374, 0.9124543, 0.38846302, 0.18476158, 0.58345926, 0.10095939, 0.6051128, noise(3997385464, 5.30356, tile_all()))",
+        ),
+    ];
+
+    #[test]
+    pub fn migrate_legacy() {
+        for (text, code) in LEGACY_FILES {
+            let (header, source) = Dna::from_text(text).unwrap();
+            assert_eq!(source.hash_scheme(), HashScheme::Legacy);
+            let generator = |dna: &mut Dna| genmap3palette(100.0, header.tiling, dna);
+            let mut dna = Dna::migrate(&source, generator);
+            assert_eq!(dna.hash_scheme(), HashScheme::Stable);
+            assert_eq!(
+                genmap3palette(100.0, header.tiling, &mut dna).get_code(),
+                code
+            );
+        }
+    }
+
+    #[test]
+    pub fn config() {
+        // Default configurations reproduce the textures of funutd 0.16.0.
+        for seed in 0..4 {
+            let palette = genmap3palette(10.0, TilingMode::All, &mut Dna::new(seed));
+            assert_eq!(palette.get_code(), PALETTE_CODES[seed as usize]);
+            let map = genmap3(10.0, TilingMode::None, &mut Dna::new(seed));
+            assert_eq!(map.get_code(), MAP_CODES[seed as usize]);
+        }
+        for seed in 0..20 {
+            // At maximum depth 0, the texture is a single basis.
+            let config = GenConfig {
+                max_depth: 0,
+                ..GenConfig::default()
+            };
+            let mut dna = Dna::new(seed);
+            genmap3_config(20.0, TilingMode::None, &config, &mut dna).unwrap();
+            let nodes: Vec<&String> = dna
+                .parameter_vector()
+                .iter()
+                .filter(|p| p.name() == "node type")
+                .map(|p| p.value())
+                .collect();
+            assert_eq!(nodes, ["basis"]);
+
+            // Disallowed nodes are never chosen.
+            let config = GenConfig {
+                unary: UnaryWeights {
+                    posterize: 0.0,
+                    ..UnaryWeights::default()
+                },
+                basis: BasisWeights {
+                    camo: 0.0,
+                    ..BasisWeights::default()
+                },
+                ..GenConfig::default()
+            };
+            let mut dna = Dna::new(seed);
+            genmap3_config(30.0, TilingMode::None, &config, &mut dna).unwrap();
+            assert!(dna
+                .parameter_vector()
+                .iter()
+                .all(|p| p.value() != "camo" && p.value() != "posterize"));
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "octaves")]
+    pub fn invalid_config() {
+        let config = GenConfig {
+            octaves: (0, 4),
+            ..GenConfig::default()
+        };
+        genmap3_config(20.0, TilingMode::None, &config, &mut Dna::new(0));
+    }
+
+    #[test]
+    pub fn budget() {
+        // Octaves multiply the cost of the fractal child.
+        let f = fractal(2.0, 4, 0, 0.5, 2.0, 0.0, 0.0, noise(1, 2.0, tile_all()));
+        assert!(f.estimated_cost() > 4.0 * noise(1, 2.0, tile_all()).estimated_cost());
+
+        let config = GenConfig {
+            max_cost: 4.0,
+            ..GenConfig::default()
+        };
+        for seed in 0..20 {
+            // Simplified trees fit in the budget.
+            let texture =
+                genmap3palette_config(50.0, TilingMode::All, &config, &mut Dna::new(seed)).unwrap();
+            assert!(texture.estimated_cost() <= 4.0);
+            let texture =
+                genmap3_config(50.0, TilingMode::All, &config, &mut Dna::new(seed)).unwrap();
+            assert!(texture.estimated_cost() <= 4.0);
+        }
+
+        // Custom nodes count toward the budget.
+        let config = GenConfig {
+            nodes: NodeRegistry::new().add_basis(
+                "expensive",
+                100.0,
+                10.0,
+                |seed, frequency, hasher, _dna| noise(seed, frequency, hasher),
+            ),
+            max_cost: 4.0,
+            ..GenConfig::default()
+        };
+        for seed in 0..20 {
+            let mut dna = Dna::new(seed);
+            genmap3_config(30.0, TilingMode::All, &config, &mut dna).unwrap();
+            assert!(dna
+                .parameter_vector()
+                .iter()
+                .all(|p| p.value() != "expensive"));
+        }
+
+        // Textures that cannot fit are not returned.
+        let config = GenConfig {
+            max_cost: 0.01,
+            ..GenConfig::default()
+        };
+        assert!(genmap3_config(30.0, TilingMode::All, &config, &mut Dna::new(1)).is_none());
+
+        // Rejected textures are over budget.
+        let (mut dna, texture) =
+            gen_within_budget(2.0, 1, 100, |dna| genmap3(30.0, TilingMode::All, dna)).unwrap();
+        assert!(texture.estimated_cost() <= 2.0);
+        dna.reset();
+        let again = genmap3(30.0, TilingMode::All, &mut dna);
+        assert_eq!(texture.get_code(), again.get_code());
+        assert!(gen_within_budget(0.0, 1, 10, |dna| genmap3(30.0, TilingMode::All, dna)).is_none());
+    }
+
+    #[test]
+    pub fn pbr() {
+        for seed in 0..10 {
+            let mut dna = Dna::new(seed);
+            let maps = gen_pbr(20.0, TilingMode::All, &mut dna);
+            // Derived maps are built on the same height.
+            let height = maps.height.get_code();
+            assert!(maps.normal.get_code().contains(&height));
+            assert!(maps.occlusion.get_code().contains(&height));
+            for i in 0..10 {
+                let p = vec3a(0.1 * i as f32, 0.37, 0.5);
+                assert!((maps.normal.at(p).length() - 1.0).abs() < 1.0e-3);
+                for map in [
+                    &maps.height,
+                    &maps.roughness,
+                    &maps.metallic,
+                    &maps.occlusion,
+                ] {
+                    let v = map.at(p);
+                    assert!(v.is_finite() && v.x == v.y && v.y == v.z);
+                    assert!(v.x >= -1.0 && v.x <= 1.0);
+                }
+                assert!(maps.albedo.at(p).is_finite());
+            }
+            // The set is regenerated from its Dna.
+            dna.reset();
+            let again = gen_pbr(20.0, TilingMode::All, &mut dna);
+            assert_eq!(maps.get_code(), again.get_code());
+        }
+    }
+
+    #[test]
+    pub fn materials() {
+        for material in Material::ALL {
+            for seed in 0..4 {
+                let mut dna = Dna::new(seed);
+                let texture = gen_material(material, TilingMode::All, &mut dna);
+                assert!(texture.at(vec3a(0.3, 0.6, 0.5)).is_finite());
+                // Mutated materials stay within their ranges.
+                let mut mutated = Dna::mutate(&dna, seed, 0.5);
+                mutated.reset();
+                gen_material(material, TilingMode::All, &mut mutated);
+                for (a, b) in dna
+                    .parameter_vector()
+                    .iter()
+                    .zip(mutated.parameter_vector().iter())
+                {
+                    assert_eq!(a.name(), b.name());
+                    if let (Some((min, max)), Some(value)) = (b.range(), b.value_vec3()) {
+                        assert!(value.cmpge(min - 1.0e-6).all() && value.cmple(max + 1.0e-6).all());
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    pub fn registry() {
+        let nodes = NodeRegistry::new()
+            .add_basis("rust stain", 100.0, 1.0, |seed, frequency, hasher, dna| {
+                let frequency = frequency * dna.f32_in("stain scale", 0.5, 2.0);
+                noise(seed, frequency, hasher)
+            })
+            .add_unary("double saturate", 100.0, 0.3, |child, dna| {
+                let amount = dna.f32_in("double amount", 1.0, 2.0);
+                saturate(amount, saturate(amount, child))
+            })
+            .add_binary("custom rotate", 100.0, 0.1, |a, b, _dna| rotate(1.0, a, b));
+        let config = GenConfig {
+            nodes,
+            ..GenConfig::default()
+        };
+        let mut found = [false; 3];
+        for seed in 0..20 {
+            let mut dna = Dna::new(seed);
+            let texture = genmap3_config(30.0, TilingMode::All, &config, &mut dna).unwrap();
+            for parameter in dna.parameter_vector() {
+                match parameter.value().as_str() {
+                    "rust stain" => found[0] = true,
+                    "double saturate" => found[1] = true,
+                    "custom rotate" => found[2] = true,
+                    _ => (),
+                }
+            }
+            // Custom nodes are regenerated from their Dna.
+            dna.reset();
+            let again = genmap3_config(30.0, TilingMode::All, &config, &mut dna).unwrap();
+            assert_eq!(texture.get_code(), again.get_code());
+        }
+        assert_eq!(found, [true; 3]);
+        assert_eq!(config.nodes.basis()[0].name(), "rust stain");
+    }
+}
@@ -84,6 +84,10 @@ impl<H: Hasher> Texture for VNoise<H> {
         result
     }
 
+    fn estimated_cost(&self) -> f32 {
+        0.75
+    }
+
     fn get_code(&self) -> String {
         format!(
             "vnoise({}, {}, {}, {})",
@@ -172,6 +176,10 @@ impl<H: Hasher> Texture for Noise<H> {
         result * 3.0
     }
 
+    fn estimated_cost(&self) -> f32 {
+        1.0
+    }
+
     fn get_code(&self) -> String {
         format!(
             "noise({}, {}, {})",
@@ -0,0 +1,263 @@
+//! Diversity-aware batch generation of textures.
+
+use super::dna::*;
+use super::evolve::*;
+use super::map3base::*;
+use super::math::*;
+use super::*;
+extern crate alloc;
+use alloc::{boxed::Box, vec::Vec};
+
+/// Width and height of the downsampled color grid in a signature.
+const SIGNATURE_GRID: usize = 4;
+
+/// Weight of spectrum distance relative to color distance.
+const SPECTRUM_WEIGHT: f32 = 0.1;
+
+/// Compact description of the appearance of a texture, for comparing textures.
+/// Consists of a downsampled color grid and the power spectrum of luminance.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Signature {
+    colors: Vec<f32>,
+    spectrum: Vec<f32>,
+}
+
+impl Signature {
+    /// Signature of a texture sampled at `size` x `size` points (size >= 4).
+    pub fn new(texture: &dyn Texture, size: usize) -> Self {
+        Self::from_samples(&Samples::new(texture, size))
+    }
+
+    /// Signature of samples (size >= 4).
+    pub fn from_samples(samples: &Samples) -> Self {
+        let n = samples.size();
+        assert!(n >= SIGNATURE_GRID);
+        let mut colors = vec![0.0; SIGNATURE_GRID * SIGNATURE_GRID * 3];
+        let mut counts = [0usize; SIGNATURE_GRID * SIGNATURE_GRID];
+        for y in 0..n {
+            for x in 0..n {
+                let cell = (y * SIGNATURE_GRID / n) * SIGNATURE_GRID + x * SIGNATURE_GRID / n;
+                let v = samples.at(x, y);
+                colors[cell * 3] += v.x;
+                colors[cell * 3 + 1] += v.y;
+                colors[cell * 3 + 2] += v.z;
+                counts[cell] += 1;
+            }
+        }
+        for (i, x) in colors.iter_mut().enumerate() {
+            *x /= counts[i / 3] as f32;
+        }
+        Self {
+            colors,
+            spectrum: spectrum(samples),
+        }
+    }
+
+    /// Distance between signatures, which is the root mean square difference of colors
+    /// plus a weighted root mean square difference of log power spectra.
+    /// Signatures must be computed at the same size.
+    pub fn distance(&self, other: &Signature) -> f32 {
+        assert_eq!(self.spectrum.len(), other.spectrum.len());
+        let rms = |x: &[f32], y: &[f32]| {
+            let sum: f32 = x.iter().zip(y.iter()).map(|(a, b)| squared(a - b)).sum();
+            sqrt(sum / x.len() as f32)
+        };
+        rms(&self.colors, &other.colors) + SPECTRUM_WEIGHT * rms(&self.spectrum, &other.spectrum)
+    }
+}
+
+/// Perceptual distance between textures, compared with signatures
+/// at `samples` x `samples` points (samples >= 4). Identical textures have distance 0.
+pub fn texture_distance(a: &dyn Texture, b: &dyn Texture, samples: usize) -> f32 {
+    Signature::new(a, samples).distance(&Signature::new(b, samples))
+}
+
+/// Novelty of a signature: mean distance to its `k` nearest neighbors in `archive`.
+/// Signatures equal to `signature` in the archive count as neighbors at distance 0.
+pub fn novelty(signature: &Signature, archive: &[Signature], k: usize) -> f32 {
+    let mut distances: Vec<f32> = archive.iter().map(|x| signature.distance(x)).collect();
+    let k = min(k, distances.len());
+    if k == 0 {
+        return f32::INFINITY;
+    }
+    distances.sort_by(|a, b| a.total_cmp(b));
+    distances[..k].iter().sum::<f32>() / k as f32
+}
+
+/// Choose `count` diverse signatures by farthest point sampling.
+/// Starts from the signature farthest from the others on average, then repeatedly adds
+/// the signature farthest from the chosen ones. Returns distinct indices in order of choice.
+/// Duplicate signatures are chosen only after all others.
+pub fn farthest_points(signatures: &[Signature], count: usize) -> Vec<usize> {
+    let n = signatures.len();
+    let count = min(count, n);
+    if count == 0 {
+        return Vec::new();
+    }
+    let mut distance = vec![0.0; n * n];
+    for i in 0..n {
+        for j in i + 1..n {
+            let d = signatures[i].distance(&signatures[j]);
+            distance[i * n + j] = d;
+            distance[j * n + i] = d;
+        }
+    }
+    let total = |i: usize| distance[i * n..(i + 1) * n].iter().sum::<f32>();
+    let mut first = 0;
+    for i in 1..n {
+        if total(i) > total(first) {
+            first = i;
+        }
+    }
+    let mut chosen = vec![first];
+    let mut is_chosen = vec![false; n];
+    is_chosen[first] = true;
+    // Distance from each signature to the nearest chosen one.
+    let mut nearest: Vec<f32> = (0..n).map(|i| distance[first * n + i]).collect();
+    while chosen.len() < count {
+        // Without a candidate at a positive distance, fall back to the first unchosen one.
+        let mut next = None;
+        for i in (0..n).filter(|&i| !is_chosen[i]) {
+            match next {
+                Some(j) if nearest[i] > nearest[j] => next = Some(i),
+                Some(_) => (),
+                None => next = Some(i),
+            }
+        }
+        let next = next.unwrap();
+        chosen.push(next);
+        is_chosen[next] = true;
+        for i in 0..n {
+            nearest[i] = min(nearest[i], distance[next * n + i]);
+        }
+    }
+    chosen
+}
+
+/// Settings for generating batches of diverse textures. Candidates are generated
+/// from random Dna and a diverse subset is chosen with `farthest_points`.
+/// Batches are deterministic for a given seed.
+#[derive(Clone)]
+pub struct DiverseBatch {
+    seed: u64,
+    candidates: usize,
+    signature_size: usize,
+}
+
+impl DiverseBatch {
+    /// Default settings: 4 candidates per chosen texture and 32 x 32 point signatures.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            candidates: 0,
+            signature_size: 32,
+        }
+    }
+
+    /// Number of candidates to choose from. The default is 4 per chosen texture.
+    pub fn candidates(mut self, candidates: usize) -> Self {
+        self.candidates = candidates;
+        self
+    }
+
+    /// Width and height of the signature sample grid (size >= 4).
+    pub fn signature_size(mut self, size: usize) -> Self {
+        assert!(size >= SIGNATURE_GRID);
+        self.signature_size = size;
+        self
+    }
+
+    /// Generate `count` diverse textures. `generator` generates a texture from Dna,
+    /// for example, `|dna| genmap3palette(20.0, TilingMode::All, dna)`.
+    /// Returns the Dna of the chosen textures, most distinct first.
+    /// Parameters of the generation pass are recorded; reset the Dna before generating again.
+    pub fn generate<G>(&self, count: usize, mut generator: G) -> Vec<Dna>
+    where
+        G: FnMut(&mut Dna) -> Box<dyn Texture>,
+    {
+        let candidates = if self.candidates > 0 {
+            self.candidates
+        } else {
+            count * 4
+        };
+        let mut rnd = Rnd::from_u64(self.seed);
+        let mut dnas = Vec::with_capacity(candidates);
+        let mut signatures = Vec::with_capacity(candidates);
+        for _ in 0..candidates {
+            let mut dna = Dna::new(rnd.u64());
+            let texture = generator(&mut dna);
+            signatures.push(Signature::new(&*texture, self.signature_size));
+            dnas.push(Some(dna));
+        }
+        farthest_points(&signatures, count)
+            .into_iter()
+            .map(|i| dnas[i].take().unwrap())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::map3gen::*;
+    use super::*;
+    use alloc::string::String;
+
+    #[test]
+    pub fn diversity() {
+        let generator = |dna: &mut Dna| genmap3palette(10.0, TilingMode::All, dna);
+        let a = generator(&mut Dna::new(1));
+        let b = generator(&mut Dna::new(2));
+        assert_eq!(texture_distance(&*a, &*a, 8), 0.0);
+        assert!(texture_distance(&*a, &*b, 8) > 0.0);
+
+        let batch = DiverseBatch::new(3).candidates(12).signature_size(8);
+        let chosen = batch.generate(4, generator);
+        assert_eq!(chosen.len(), 4);
+        let signature = |dna: &Dna| {
+            let mut dna = dna.clone();
+            dna.reset();
+            Signature::new(&*generator(&mut dna), 8)
+        };
+        let signatures: Vec<Signature> = chosen.iter().map(signature).collect();
+        for i in 0..4 {
+            assert!(novelty(&signatures[i], &signatures, 2) > 0.0);
+        }
+        // Farthest point sampling spreads out evenly on a line.
+        let points: Vec<Signature> = (0..5)
+            .map(|i| {
+                let value = i as f32 / 4.0;
+                Signature::new(&Constant(value), 8)
+            })
+            .collect();
+        let mut chosen = farthest_points(&points, 3);
+        chosen[..2].sort();
+        assert_eq!(chosen, [0, 4, 2]);
+
+        // Identical candidates are each chosen once.
+        let points = vec![Signature::new(&Constant(0.5), 8); 3];
+        let mut chosen = farthest_points(&points, 3);
+        chosen.sort();
+        assert_eq!(chosen, [0, 1, 2]);
+        let batch = DiverseBatch::new(4).candidates(4).signature_size(8);
+        let chosen = batch.generate(3, |dna: &mut Dna| {
+            dna.f32("value");
+            Box::new(Constant(0.5))
+        });
+        assert_eq!(chosen.len(), 3);
+    }
+
+    #[derive(Clone)]
+    struct Constant(f32);
+
+    impl Texture for Constant {
+        fn at_frequency(&self, _point: Vec3a, _frequency: Option<f32>) -> Vec3a {
+            Vec3a::splat(self.0)
+        }
+        fn get_code(&self) -> String {
+            format!("Constant({:?})", self.0)
+        }
+        fn get_basis_code(&self) -> String {
+            self.get_code()
+        }
+    }
+}
@@ -0,0 +1,249 @@
+//! Periodic pattern bases: stripes, rings and spherical waves.
+
+use super::ease::*;
+use super::hash::*;
+use super::map3base::*;
+use super::math::*;
+use super::*;
+extern crate alloc;
+use alloc::{boxed::Box, string::String};
+
+/// Periodic wave profile with period 1 shaped by `ease`. Peaks at t = 0.25.
+#[inline]
+pub fn wave_profile(ease: &Ease, t: f32) -> f32 {
+    wave(|x| ease.at(x), t * 4.0)
+}
+
+/// Applies the profile to each component with a seed dependent phase.
+#[inline]
+pub(crate) fn profile3(ease: &Ease, phase: Vec3a, t: f32) -> Vec3a {
+    vec3a(
+        wave_profile(ease, t + phase.x),
+        wave_profile(ease, t + phase.y),
+        wave_profile(ease, t + phase.z),
+    )
+}
+
+/// Component phases. The first component always has zero phase.
+#[inline]
+pub(crate) fn phase(seed: u64) -> Vec3a {
+    hash_01(hash64d(seed)) * vec3a(0.0, 1.0, 1.0)
+}
+
+/// Parallel stripes perpendicular to an axis, one period per cell.
+/// Tiles with tiling hashers.
+#[derive(Clone)]
+pub struct Stripes<H: Hasher> {
+    seed: u64,
+    frequency: f32,
+    axis: Axis,
+    ease: Ease,
+    hasher: H,
+}
+
+pub fn stripes<H: 'static + Hasher>(
+    seed: u64,
+    frequency: f32,
+    axis: Axis,
+    ease: Ease,
+    hasher: H,
+) -> Box<dyn Texture> {
+    Box::new(Stripes {
+        seed,
+        frequency,
+        axis,
+        ease,
+        hasher,
+    })
+}
+
+pub fn stripes_basis<H: 'static + Hasher>(
+    seed: u64,
+    axis: Axis,
+    ease: Ease,
+    hasher: H,
+) -> Box<dyn Texture> {
+    stripes(seed, 1.0, axis, ease, hasher)
+}
+
+impl<H: Hasher> Texture for Stripes<H> {
+    fn at_frequency(&self, point: Vec3a, frequency: Option<f32>) -> Vec3a {
+        let frequency = frequency.unwrap_or(self.frequency);
+        let basis = self.hasher.query(self.seed, frequency, point);
+        profile3(&self.ease, phase(self.seed), self.axis.along(basis.d))
+    }
+
+    fn estimated_cost(&self) -> f32 {
+        0.1
+    }
+
+    fn get_code(&self) -> String {
+        format!(
+            "stripes({}, {:?}, {}, {}, {})",
+            self.seed,
+            self.frequency,
+            self.axis.get_code(),
+            self.ease.get_code(),
+            self.hasher.get_code()
+        )
+    }
+
+    fn get_basis_code(&self) -> String {
+        format!(
+            "stripes_basis({}, {}, {}, {})",
+            self.seed,
+            self.axis.get_code(),
+            self.ease.get_code(),
+            self.hasher.get_code()
+        )
+    }
+}
+
+/// Concentric rings around an axis through the center of each cell.
+/// The number of rings from the center to the cell edge is `rings`.
+/// Ring distance is continuous across cell boundaries, so tiling hashers are respected.
+#[derive(Clone)]
+pub struct Rings<H: Hasher> {
+    seed: u64,
+    frequency: f32,
+    axis: Axis,
+    rings: f32,
+    ease: Ease,
+    hasher: H,
+}
+
+pub fn rings<H: 'static + Hasher>(
+    seed: u64,
+    frequency: f32,
+    axis: Axis,
+    rings: f32,
+    ease: Ease,
+    hasher: H,
+) -> Box<dyn Texture> {
+    Box::new(Rings {
+        seed,
+        frequency,
+        axis,
+        rings,
+        ease,
+        hasher,
+    })
+}
+
+pub fn rings_basis<H: 'static + Hasher>(
+    seed: u64,
+    axis: Axis,
+    rings: f32,
+    ease: Ease,
+    hasher: H,
+) -> Box<dyn Texture> {
+    self::rings(seed, 1.0, axis, rings, ease, hasher)
+}
+
+impl<H: Hasher> Texture for Rings<H> {
+    fn at_frequency(&self, point: Vec3a, frequency: Option<f32>) -> Vec3a {
+        let frequency = frequency.unwrap_or(self.frequency);
+        let basis = self.hasher.query(self.seed, frequency, point);
+        let (u, v) = self.axis.across(basis.d - Vec3a::splat(0.5));
+        let r = sqrt(u * u + v * v) * 2.0;
+        profile3(&self.ease, phase(self.seed), r * self.rings)
+    }
+
+    fn estimated_cost(&self) -> f32 {
+        0.1
+    }
+
+    fn get_code(&self) -> String {
+        format!(
+            "rings({}, {:?}, {}, {:?}, {}, {})",
+            self.seed,
+            self.frequency,
+            self.axis.get_code(),
+            self.rings,
+            self.ease.get_code(),
+            self.hasher.get_code()
+        )
+    }
+
+    fn get_basis_code(&self) -> String {
+        format!(
+            "rings_basis({}, {}, {:?}, {}, {})",
+            self.seed,
+            self.axis.get_code(),
+            self.rings,
+            self.ease.get_code(),
+            self.hasher.get_code()
+        )
+    }
+}
+
+/// Concentric spherical waves around the center of each cell.
+/// The number of waves from the center to the cell face is `waves`.
+/// Wave distance is continuous across cell boundaries, so tiling hashers are respected.
+#[derive(Clone)]
+pub struct SphericalWaves<H: Hasher> {
+    seed: u64,
+    frequency: f32,
+    waves: f32,
+    ease: Ease,
+    hasher: H,
+}
+
+pub fn spherical_waves<H: 'static + Hasher>(
+    seed: u64,
+    frequency: f32,
+    waves: f32,
+    ease: Ease,
+    hasher: H,
+) -> Box<dyn Texture> {
+    Box::new(SphericalWaves {
+        seed,
+        frequency,
+        waves,
+        ease,
+        hasher,
+    })
+}
+
+pub fn spherical_waves_basis<H: 'static + Hasher>(
+    seed: u64,
+    waves: f32,
+    ease: Ease,
+    hasher: H,
+) -> Box<dyn Texture> {
+    spherical_waves(seed, 1.0, waves, ease, hasher)
+}
+
+impl<H: Hasher> Texture for SphericalWaves<H> {
+    fn at_frequency(&self, point: Vec3a, frequency: Option<f32>) -> Vec3a {
+        let frequency = frequency.unwrap_or(self.frequency);
+        let basis = self.hasher.query(self.seed, frequency, point);
+        let r = (basis.d - Vec3a::splat(0.5)).length() * 2.0;
+        profile3(&self.ease, phase(self.seed), r * self.waves)
+    }
+
+    fn estimated_cost(&self) -> f32 {
+        0.1
+    }
+
+    fn get_code(&self) -> String {
+        format!(
+            "spherical_waves({}, {:?}, {:?}, {}, {})",
+            self.seed,
+            self.frequency,
+            self.waves,
+            self.ease.get_code(),
+            self.hasher.get_code()
+        )
+    }
+
+    fn get_basis_code(&self) -> String {
+        format!(
+            "spherical_waves_basis({}, {:?}, {}, {})",
+            self.seed,
+            self.waves,
+            self.ease.get_code(),
+            self.hasher.get_code()
+        )
+    }
+}
@@ -4,10 +4,19 @@ pub use super::color::*;
 pub use super::distance::*;
 pub use super::dna::*;
 pub use super::ease::*;
+pub use super::evolve::*;
+#[cfg(any(feature = "png", feature = "exr"))]
+pub use super::export::*;
+pub use super::gabor::*;
 pub use super::map3::*;
 pub use super::map3base::*;
 pub use super::map3gen::*;
 pub use super::noise::*;
+pub use super::novelty::*;
+pub use super::pattern::*;
+pub use super::render::*;
 pub use super::rnd::*;
+pub use super::spots::*;
+pub use super::tiles::*;
 pub use super::vec::*;
 pub use super::voronoi::*;
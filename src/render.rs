@@ -0,0 +1,340 @@
+//! Rendering textures to images.
+
+use super::color::*;
+use super::map3base::*;
+use super::math::*;
+use super::*;
+extern crate alloc;
+use alloc::vec::Vec;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+
+/// Pixel format of rendered images.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// 8-bit RGB.
+    Rgb8,
+    /// 8-bit RGBA with opaque alpha.
+    Rgba8,
+    /// 16-bit RGB.
+    Rgb16,
+    /// 32-bit float RGB.
+    Rgb32F,
+}
+
+impl PixelFormat {
+    /// Number of channels per pixel.
+    pub fn channels(&self) -> usize {
+        match self {
+            PixelFormat::Rgba8 => 4,
+            _ => 3,
+        }
+    }
+}
+
+/// Interpretation of texture values when converting them to pixels.
+/// Texture values in -1...1 are mapped to pixel values in 0...1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorSpace {
+    /// Values are sRGB encoded colors, as produced by `palette`, and are stored as is.
+    /// Supersamples are averaged in linear space.
+    Srgb,
+    /// Values are sRGB encoded colors and are stored converted to linear.
+    Linear,
+    /// Values are data such as heights or normals and are stored as is.
+    /// Float pixels store texture values without mapping or clamping.
+    Data,
+}
+
+/// Rectangle of the XY plane to render. Pixel coordinates map linearly from `min` to `max`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Region {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Region {
+    /// Region from `min` to `max`.
+    pub fn new(min: Vec2, max: Vec2) -> Self {
+        Self { min, max }
+    }
+
+    /// Unit square from the origin, which covers a tiling texture exactly once.
+    pub fn unit() -> Self {
+        Self::new(Vec2::ZERO, Vec2::ONE)
+    }
+}
+
+impl Default for Region {
+    fn default() -> Self {
+        Self::unit()
+    }
+}
+
+/// Output format of `render_2d`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct RenderFormat {
+    pixel_format: PixelFormat,
+    color_space: ColorSpace,
+    samples: usize,
+}
+
+impl RenderFormat {
+    /// Pixels in the given format. Values are sRGB colors and are not supersampled by default.
+    pub fn new(pixel_format: PixelFormat) -> Self {
+        Self {
+            pixel_format,
+            color_space: ColorSpace::Srgb,
+            samples: 1,
+        }
+    }
+
+    /// Set interpretation of texture values.
+    pub fn color_space(mut self, color_space: ColorSpace) -> Self {
+        self.color_space = color_space;
+        self
+    }
+
+    /// Supersample each pixel on a grid of `samples` x `samples` points (samples >= 1).
+    pub fn supersample(mut self, samples: usize) -> Self {
+        assert!(samples >= 1);
+        self.samples = samples;
+        self
+    }
+
+    /// Pixel format.
+    pub fn pixel_format(&self) -> PixelFormat {
+        self.pixel_format
+    }
+
+    /// Interpretation of texture values.
+    pub fn get_color_space(&self) -> ColorSpace {
+        self.color_space
+    }
+
+    /// Number of samples per pixel along each axis.
+    pub fn samples(&self) -> usize {
+        self.samples
+    }
+}
+
+impl From<PixelFormat> for RenderFormat {
+    fn from(pixel_format: PixelFormat) -> Self {
+        Self::new(pixel_format)
+    }
+}
+
+/// Pixel data of an image, with channels interleaved and rows from top to bottom.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Pixels {
+    U8(Vec<u8>),
+    U16(Vec<u16>),
+    F32(Vec<f32>),
+}
+
+/// Rendered image.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub format: PixelFormat,
+    pub pixels: Pixels,
+}
+
+impl Image {
+    /// 8-bit pixel data, if the format is 8-bit.
+    pub fn as_u8(&self) -> Option<&[u8]> {
+        match &self.pixels {
+            Pixels::U8(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    /// 16-bit pixel data, if the format is 16-bit.
+    pub fn as_u16(&self) -> Option<&[u16]> {
+        match &self.pixels {
+            Pixels::U16(data) => Some(data),
+            _ => None,
+        }
+    }
+
+    /// Float pixel data, if the format is float.
+    pub fn as_f32(&self) -> Option<&[f32]> {
+        match &self.pixels {
+            Pixels::F32(data) => Some(data),
+            _ => None,
+        }
+    }
+}
+
+/// Render a `width` x `height` pixel slice of the texture at depth `z` (width, height > 0).
+/// Pixel (0, 0) is at the top left corner of the region, which is the minimum corner.
+/// Pixels are sampled at their centers; supersamples are centered in a grid within the pixel.
+/// With the `rayon` feature, rows are rendered in parallel.
+pub fn render_2d(
+    texture: &dyn Texture,
+    width: usize,
+    height: usize,
+    region: Region,
+    z: f32,
+    format: &RenderFormat,
+) -> Image {
+    let values = render_values(texture, width, height, region, z, format);
+    let pixels = match format.pixel_format {
+        PixelFormat::Rgb8 => Pixels::U8(pixels_u8(&values, false, format.color_space)),
+        PixelFormat::Rgba8 => Pixels::U8(pixels_u8(&values, true, format.color_space)),
+        PixelFormat::Rgb16 => Pixels::U16(pixels_u16(&values, format.color_space)),
+        PixelFormat::Rgb32F => Pixels::F32(values.iter().flat_map(|v| v.to_array()).collect()),
+    };
+    Image {
+        width,
+        height,
+        format: format.pixel_format,
+        pixels,
+    }
+}
+
+/// Render pixel values of a slice of the texture, row by row. See `render_2d`.
+pub(crate) fn render_values(
+    texture: &dyn Texture,
+    width: usize,
+    height: usize,
+    region: Region,
+    z: f32,
+    format: &RenderFormat,
+) -> Vec<Vec3a> {
+    assert!(width > 0 && height > 0);
+    let mut values = vec![Vec3a::zero(); width * height];
+    let render_row = |(y, row): (usize, &mut [Vec3a])| {
+        for (x, value) in row.iter_mut().enumerate() {
+            *value = render_pixel(texture, x, y, width, height, region, z, format);
+        }
+    };
+    #[cfg(feature = "rayon")]
+    values
+        .par_chunks_mut(width)
+        .enumerate()
+        .for_each(render_row);
+    #[cfg(not(feature = "rayon"))]
+    values.chunks_mut(width).enumerate().for_each(render_row);
+    values
+}
+
+/// Map a pixel value to 0...1. Data values are mapped from -1...1.
+fn unit(x: f32, color_space: ColorSpace) -> f32 {
+    if color_space == ColorSpace::Data {
+        clamp01(x * 0.5 + 0.5)
+    } else {
+        x
+    }
+}
+
+/// Convert pixel values to 8-bit RGB, or RGBA with opaque alpha.
+pub(crate) fn pixels_u8(values: &[Vec3a], alpha: bool, color_space: ColorSpace) -> Vec<u8> {
+    let channels = if alpha { 4 } else { 3 };
+    let mut data = Vec::with_capacity(values.len() * channels);
+    for v in values {
+        for i in 0..3 {
+            data.push(round(unit(v[i], color_space) * 255.0) as u8);
+        }
+        if alpha {
+            data.push(255);
+        }
+    }
+    data
+}
+
+/// Convert pixel values to 16-bit RGB.
+pub(crate) fn pixels_u16(values: &[Vec3a], color_space: ColorSpace) -> Vec<u16> {
+    values
+        .iter()
+        .flat_map(|v| (0..3).map(move |i| round(unit(v[i], color_space) * 65535.0) as u16))
+        .collect()
+}
+
+/// Render a pixel. Returns texture values in the `Data` color space
+/// and unclamped pixel values in 0...1 otherwise.
+#[allow(clippy::too_many_arguments)]
+fn render_pixel(
+    texture: &dyn Texture,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    region: Region,
+    z: f32,
+    format: &RenderFormat,
+) -> Vec3a {
+    let n = format.samples;
+    let scale = (region.max - region.min) / vec2(width as f32, height as f32);
+    let mut total = Vec3a::zero();
+    for sy in 0..n {
+        for sx in 0..n {
+            let p = region.min
+                + vec2(
+                    x as f32 + (sx as f32 + 0.5) / n as f32,
+                    y as f32 + (sy as f32 + 0.5) / n as f32,
+                ) * scale;
+            let v = texture.at(vec3a(p.x, p.y, z));
+            total += match format.color_space {
+                ColorSpace::Data => v,
+                // Colors are averaged in linear space.
+                _ if n > 1 || format.color_space == ColorSpace::Linear => (v * 0.5
+                    + Vec3a::splat(0.5))
+                .to_array()
+                .map(srgb_transfer_function_inv)
+                .into(),
+                _ => clamp01(v * 0.5 + Vec3a::splat(0.5)),
+            };
+        }
+    }
+    let v = total / (n * n) as f32;
+    if format.color_space == ColorSpace::Srgb && n > 1 {
+        v.to_array().map(srgb_transfer_function).into()
+    } else {
+        v
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::map3::*;
+    use super::super::noise::*;
+    use super::*;
+
+    #[test]
+    pub fn render() {
+        let format = RenderFormat::new(PixelFormat::Rgba8);
+        let image = render_2d(&zero(), 3, 2, Region::unit(), 0.0, &format);
+        assert_eq!(image.as_u8().unwrap(), [128, 128, 128, 255].repeat(6));
+
+        // Linear values are darker than sRGB values.
+        let format = RenderFormat::new(PixelFormat::Rgb32F).color_space(ColorSpace::Linear);
+        let image = render_2d(&zero(), 1, 1, Region::unit(), 0.0, &format);
+        assert!((image.as_f32().unwrap()[0] - 0.214).abs() < 1.0e-3);
+
+        // Data is stored unmapped in floats and matches texture values at pixel centers.
+        let texture = noise(1, 4.0, tile_all());
+        let format = RenderFormat::new(PixelFormat::Rgb32F).color_space(ColorSpace::Data);
+        let region = Region::new(vec2(0.5, 0.25), vec2(1.5, 0.75));
+        let image = render_2d(&*texture, 4, 2, region, 0.5, &format);
+        let data = image.as_f32().unwrap();
+        let v = texture.at(vec3a(1.375, 0.625, 0.5));
+        assert_eq!(&data[(4 + 3) * 3..(4 + 4) * 3], &v.to_array());
+
+        // Supersampling averages samples.
+        let format = RenderFormat::new(PixelFormat::Rgb16)
+            .color_space(ColorSpace::Data)
+            .supersample(4);
+        let image = render_2d(&*texture, 8, 8, Region::unit(), 0.5, &format);
+        assert_eq!(image.as_u16().unwrap().len(), 8 * 8 * 3);
+        let mut total = Vec3a::zero();
+        for y in 0..4 {
+            for x in 0..4 {
+                total += texture.at(vec3a((x as f32 + 0.5) / 32.0, (y as f32 + 0.5) / 32.0, 0.5));
+            }
+        }
+        let expected = round(clamp01(total.x / 16.0 * 0.5 + 0.5) * 65535.0) as u16;
+        assert_eq!(image.as_u16().unwrap()[0], expected);
+    }
+}
@@ -424,6 +424,14 @@ impl Rnd {
         self.f64() < p
     }
 
+    /// Generates the next normally distributed number with zero mean and unit variance.
+    pub fn normal(&mut self) -> f64 {
+        // Box-Muller transform. The first number is in (0, 1] to avoid the logarithm of zero.
+        let u = 1.0 - self.f64();
+        let v = self.f64();
+        libm::sqrt(-2.0 * libm::log(u)) * libm::cos(core::f64::consts::TAU * v)
+    }
+
     /// Fills a destination slice with random bytes.
     pub fn fill_bytes(&mut self, dest: &mut [u8]) {
         let bytes = dest.len();
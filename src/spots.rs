@@ -0,0 +1,186 @@
+//! Sparse spot stamping basis.
+
+use super::ease::*;
+use super::hash::*;
+use super::map3base::*;
+use super::math::*;
+use super::*;
+extern crate alloc;
+use alloc::{boxed::Box, string::String};
+
+/// Profiles stamped at features of the spot basis.
+#[derive(Clone)]
+pub enum Stamp {
+    /// Bowl shaped depression surrounded by a raised rim.
+    Crater,
+    /// Round dot.
+    Dot,
+    /// Ring near the edge of the feature.
+    Ring,
+    /// Texture evaluated in local feature coordinates, which are in -1...1 inside the feature.
+    Texture(Box<dyn Texture>),
+}
+
+impl Stamp {
+    pub fn get_code(&self) -> String {
+        match self {
+            Stamp::Crater => String::from("Stamp::Crater"),
+            Stamp::Dot => String::from("Stamp::Dot"),
+            Stamp::Ring => String::from("Stamp::Ring"),
+            Stamp::Texture(texture) => format!("Stamp::Texture({})", texture.get_code()),
+        }
+    }
+}
+
+/// Spot basis. Features are scattered sparsely in cells and stamped with a profile.
+/// Feature radii are distributed log-uniformly between minimum and maximum radius.
+/// Radii are relative to cell size and at most 1.
+#[derive(Clone)]
+pub struct Spots<H: Hasher> {
+    seed: u64,
+    frequency: f32,
+    density: f32,
+    min_radius: f32,
+    max_radius: f32,
+    stamp: Stamp,
+    ease: Ease,
+    hasher: H,
+}
+
+/// Spot basis. Density is the average number of features per cell (0 < density <= 4).
+/// Radii are relative to cell size (0 < min_radius <= max_radius <= 1).
+/// The ease shapes the edges of stamps.
+pub fn spots<H: 'static + Hasher>(
+    seed: u64,
+    frequency: f32,
+    density: f32,
+    min_radius: f32,
+    max_radius: f32,
+    stamp: Stamp,
+    ease: Ease,
+    hasher: H,
+) -> Box<dyn Texture> {
+    assert!(density > 0.0 && density <= 4.0);
+    assert!(min_radius > 0.0 && min_radius <= max_radius && max_radius <= 1.0);
+    Box::new(Spots {
+        seed,
+        frequency,
+        density,
+        min_radius,
+        max_radius,
+        stamp,
+        ease,
+        hasher,
+    })
+}
+
+pub fn spots_basis<H: 'static + Hasher>(
+    seed: u64,
+    density: f32,
+    min_radius: f32,
+    max_radius: f32,
+    stamp: Stamp,
+    ease: Ease,
+    hasher: H,
+) -> Box<dyn Texture> {
+    spots(
+        seed, 1.0, density, min_radius, max_radius, stamp, ease, hasher,
+    )
+}
+
+impl<H: Hasher> Spots<H> {
+    /// Stamp value at relative distance `r` from feature center (0 <= r < 1).
+    /// The local position is in -1...1 inside the feature.
+    fn stamp_at(&self, r: f32, local: Vec3a) -> Vec3a {
+        match &self.stamp {
+            Stamp::Crater => {
+                let rim = 0.7;
+                let h = if r < rim {
+                    1.5 * squared(r / rim) - 1.0
+                } else {
+                    0.5 * self.ease.at((1.0 - r) / (1.0 - rim))
+                };
+                Vec3a::splat(h)
+            }
+            Stamp::Dot => Vec3a::splat(self.ease.at(1.0 - r)),
+            Stamp::Ring => Vec3a::splat(self.ease.at(max(0.0, 1.0 - abs(r - 0.7) / 0.3))),
+            Stamp::Texture(texture) => texture.at(local) * self.ease.at(min(1.0, (1.0 - r) * 4.0)),
+        }
+    }
+}
+
+impl<H: Hasher> Texture for Spots<H> {
+    fn at_frequency(&self, point: Vec3a, frequency: Option<f32>) -> Vec3a {
+        let frequency = frequency.unwrap_or(self.frequency);
+        let basis = self.hasher.query(self.seed, frequency, point);
+        let extra = self.density - self.density.floor();
+        let mut result = Vec3a::zero();
+
+        for dx in -1..=1 {
+            let hx = self.hasher.hash_x(&basis, 0, dx);
+            for dy in -1..=1 {
+                let hxy = self.hasher.hash_y(&basis, hx, dy);
+                let mut offset = Vec3a::new(dx as f32, dy as f32, 0.0) - basis.d;
+                for dz in -1..=1 {
+                    let mut hash = self.hasher.hash_z(&basis, hxy, dz);
+                    // The fractional part of density is the probability of an extra feature.
+                    let u = (hash >> 40) as f32 / (1 << 24) as f32;
+                    let n = self.density as usize + if u < extra { 1 } else { 0 };
+                    // Offset points from cell corner to queried point.
+                    offset = vec3a(offset.x, offset.y, dz as f32 - basis.d.z);
+                    for i in 0..n {
+                        // Feature location.
+                        let p = hash_01(hash);
+                        let delta = p + offset;
+                        let radius =
+                            xerp(self.min_radius, self.max_radius, hash_01(hash64d(hash)).x);
+                        let distance2 = delta.length_squared();
+                        if distance2 < radius * radius {
+                            let r = sqrt(distance2) / radius;
+                            result += self.stamp_at(r, -delta / radius);
+                        }
+                        if i + 1 < n {
+                            hash = hash64c(hash);
+                        }
+                    }
+                }
+            }
+        }
+        result
+    }
+
+    fn estimated_cost(&self) -> f32 {
+        let stamp = match &self.stamp {
+            Stamp::Texture(texture) => texture.estimated_cost(),
+            _ => 0.0,
+        };
+        0.3 + self.density * (1.1 + stamp)
+    }
+
+    fn get_code(&self) -> String {
+        format!(
+            "spots({}, {:?}, {:?}, {:?}, {:?}, {}, {}, {})",
+            self.seed,
+            self.frequency,
+            self.density,
+            self.min_radius,
+            self.max_radius,
+            self.stamp.get_code(),
+            self.ease.get_code(),
+            self.hasher.get_code()
+        )
+    }
+
+    fn get_basis_code(&self) -> String {
+        format!(
+            "spots_basis({}, {:?}, {:?}, {:?}, {}, {}, {})",
+            self.seed,
+            self.density,
+            self.min_radius,
+            self.max_radius,
+            self.stamp.get_code(),
+            self.ease.get_code(),
+            self.hasher.get_code()
+        )
+    }
+}
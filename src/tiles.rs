@@ -0,0 +1,322 @@
+//! Tile pattern bases: Truchet and Wang tiles.
+
+use super::ease::*;
+use super::hash::*;
+use super::map3base::*;
+use super::math::*;
+use super::pattern::*;
+use super::*;
+extern crate alloc;
+use alloc::{boxed::Box, string::String, vec::Vec};
+
+/// Hashes the cell at offset (du, dv) in the plane perpendicular to `axis`.
+#[inline]
+fn plane_hash<H: Hasher>(hasher: &H, basis: &Basis, axis: Axis, du: i32, dv: i32) -> u64 {
+    let (dx, dy, dz) = match axis {
+        Axis::X => (0, du, dv),
+        Axis::Y => (dv, 0, du),
+        Axis::Z => (du, dv, 0),
+    };
+    let hx = hasher.hash_x(basis, 0, dx);
+    let hxy = hasher.hash_y(basis, hx, dy);
+    hasher.hash_z(basis, hxy, dz)
+}
+
+/// Distance from `q` to the line segment from `a` to `b`.
+#[inline]
+fn segment_distance(q: (f32, f32), a: (f32, f32), b: (f32, f32)) -> f32 {
+    let (ux, uy) = (b.0 - a.0, b.1 - a.1);
+    let (wx, wy) = (q.0 - a.0, q.1 - a.1);
+    let t = clamp01((wx * ux + wy * uy) / (ux * ux + uy * uy));
+    sqrt(squared(wx - t * ux) + squared(wy - t * uy))
+}
+
+/// Distance from `q` to the quarter circle of radius 0.5 centered at corner `c`
+/// that lies inside the unit cell. The signs point from the corner into the cell.
+#[inline]
+fn arc_distance(q: (f32, f32), c: (f32, f32), sign: (f32, f32)) -> f32 {
+    let (wx, wy) = ((q.0 - c.0) * sign.0, (q.1 - c.1) * sign.1);
+    if wx >= 0.0 && wy >= 0.0 {
+        abs(sqrt(wx * wx + wy * wy) - 0.5)
+    } else {
+        min(
+            sqrt(squared(wx - 0.5) + wy * wy),
+            sqrt(wx * wx + squared(wy - 0.5)),
+        )
+    }
+}
+
+/// Tiles of the Truchet basis. Each cell picks a random variant of the tile.
+#[derive(Clone)]
+pub enum TruchetTile {
+    /// Pair of quarter circle arcs connecting the midpoints of adjacent edges.
+    Arcs,
+    /// Diagonal line connecting opposite corners.
+    Diagonals,
+    /// Texture evaluated in local cell coordinates, which are in 0...1, rotated by a multiple
+    /// of 90 degrees. Tiles connect seamlessly if the texture is compatible with its rotations
+    /// at cell edges.
+    Texture(Box<dyn Texture>),
+}
+
+impl TruchetTile {
+    pub fn get_code(&self) -> String {
+        match self {
+            TruchetTile::Arcs => String::from("TruchetTile::Arcs"),
+            TruchetTile::Diagonals => String::from("TruchetTile::Diagonals"),
+            TruchetTile::Texture(texture) => {
+                format!("TruchetTile::Texture({})", texture.get_code())
+            }
+        }
+    }
+}
+
+/// Truchet tiling in the plane perpendicular to an axis.
+/// Built-in tiles are shaded by distance to the nearest curve, which is continuous across cells.
+#[derive(Clone)]
+pub struct Truchet<H: Hasher> {
+    seed: u64,
+    frequency: f32,
+    axis: Axis,
+    tile: TruchetTile,
+    ease: Ease,
+    hasher: H,
+}
+
+/// Truchet tiling in the plane perpendicular to `axis`.
+/// The ease shapes the profile of built-in tiles.
+pub fn truchet<H: 'static + Hasher>(
+    seed: u64,
+    frequency: f32,
+    axis: Axis,
+    tile: TruchetTile,
+    ease: Ease,
+    hasher: H,
+) -> Box<dyn Texture> {
+    Box::new(Truchet {
+        seed,
+        frequency,
+        axis,
+        tile,
+        ease,
+        hasher,
+    })
+}
+
+pub fn truchet_basis<H: 'static + Hasher>(
+    seed: u64,
+    axis: Axis,
+    tile: TruchetTile,
+    ease: Ease,
+    hasher: H,
+) -> Box<dyn Texture> {
+    truchet(seed, 1.0, axis, tile, ease, hasher)
+}
+
+impl<H: Hasher> Texture for Truchet<H> {
+    fn at_frequency(&self, point: Vec3a, frequency: Option<f32>) -> Vec3a {
+        let basis = self
+            .hasher
+            .query(self.seed, frequency.unwrap_or(self.frequency), point);
+        let (u, v) = self.axis.across(basis.d);
+        if let TruchetTile::Texture(texture) = &self.tile {
+            let (u, v) = match plane_hash(&self.hasher, &basis, self.axis, 0, 0) & 3 {
+                0 => (u, v),
+                1 => (1.0 - v, u),
+                2 => (1.0 - u, 1.0 - v),
+                _ => (v, 1.0 - u),
+            };
+            return texture.at_frequency(
+                self.axis.compose(self.axis.along(basis.d), (u, v)),
+                frequency,
+            );
+        }
+        // Curves of neighboring cells may be closer than those of the current cell.
+        let mut distance = f32::INFINITY;
+        for du in -1..=1 {
+            for dv in -1..=1 {
+                let flip = plane_hash(&self.hasher, &basis, self.axis, du, dv) & 1 == 1;
+                let q = (u - du as f32, v - dv as f32);
+                let d = match (&self.tile, flip) {
+                    (TruchetTile::Arcs, false) => min(
+                        arc_distance(q, (0.0, 0.0), (1.0, 1.0)),
+                        arc_distance(q, (1.0, 1.0), (-1.0, -1.0)),
+                    ),
+                    (TruchetTile::Arcs, true) => min(
+                        arc_distance(q, (1.0, 0.0), (-1.0, 1.0)),
+                        arc_distance(q, (0.0, 1.0), (1.0, -1.0)),
+                    ),
+                    (_, false) => segment_distance(q, (0.0, 0.0), (1.0, 1.0)),
+                    (_, true) => segment_distance(q, (1.0, 0.0), (0.0, 1.0)),
+                };
+                distance = min(distance, d);
+            }
+        }
+        // Profile peaks on the curves.
+        profile3(&self.ease, phase(self.seed), distance + 0.25)
+    }
+
+    fn estimated_cost(&self) -> f32 {
+        match &self.tile {
+            TruchetTile::Texture(texture) => 0.1 + texture.estimated_cost(),
+            _ => 0.3,
+        }
+    }
+
+    fn get_code(&self) -> String {
+        format!(
+            "truchet({}, {:?}, {}, {}, {}, {})",
+            self.seed,
+            self.frequency,
+            self.axis.get_code(),
+            self.tile.get_code(),
+            self.ease.get_code(),
+            self.hasher.get_code()
+        )
+    }
+
+    fn get_basis_code(&self) -> String {
+        format!(
+            "truchet_basis({}, {}, {}, {}, {})",
+            self.seed,
+            self.axis.get_code(),
+            self.tile.get_code(),
+            self.ease.get_code(),
+            self.hasher.get_code()
+        )
+    }
+}
+
+/// Wang tiling in the plane perpendicular to an axis.
+/// Each cell edge and corner is assigned a color that is shared with the neighboring cells.
+/// This approximates Wang tiles: instead of choosing a discrete tile with matching edges,
+/// cell contents blend the textures of its edge and corner colors so that on each edge,
+/// only the colors of that edge and its corners contribute.
+#[derive(Clone)]
+pub struct Wang<H: Hasher> {
+    seed: u64,
+    frequency: f32,
+    axis: Axis,
+    tiles: Vec<Box<dyn Texture>>,
+    hasher: H,
+}
+
+/// Wang tiling in the plane perpendicular to `axis` with one texture per edge color.
+/// Textures are evaluated in local cell coordinates, which are in 0...1.
+/// The result is seamless if the textures tile with period 1, for example with `tile_all`.
+pub fn wang<H: 'static + Hasher>(
+    seed: u64,
+    frequency: f32,
+    axis: Axis,
+    tiles: Vec<Box<dyn Texture>>,
+    hasher: H,
+) -> Box<dyn Texture> {
+    assert!(!tiles.is_empty());
+    Box::new(Wang {
+        seed,
+        frequency,
+        axis,
+        tiles,
+        hasher,
+    })
+}
+
+pub fn wang_basis<H: 'static + Hasher>(
+    seed: u64,
+    axis: Axis,
+    tiles: Vec<Box<dyn Texture>>,
+    hasher: H,
+) -> Box<dyn Texture> {
+    wang(seed, 1.0, axis, tiles, hasher)
+}
+
+impl<H: Hasher> Wang<H> {
+    fn get_tiles_code(&self) -> String {
+        let mut code = String::new();
+        for (i, tile) in self.tiles.iter().enumerate() {
+            if i > 0 {
+                code.push_str(", ");
+            }
+            code.push_str(&tile.get_code());
+        }
+        code
+    }
+}
+
+impl<H: Hasher> Texture for Wang<H> {
+    fn at_frequency(&self, point: Vec3a, frequency: Option<f32>) -> Vec3a {
+        let basis = self
+            .hasher
+            .query(self.seed, frequency.unwrap_or(self.frequency), point);
+        let (u, v) = self.axis.across(basis.d);
+        let n = self.tiles.len() as u64;
+        let hash = |du, dv| plane_hash(&self.hasher, &basis, self.axis, du, dv);
+        let h00 = hash(0, 0);
+        // Each cell owns its high U edge, high V edge and low corner.
+        let u_edge = |h: u64| (hash64d(h) % n) as usize;
+        let v_edge = |h: u64| (hash64e(h) % n) as usize;
+        let corner = |h: u64| (hash64g(h) % n) as usize;
+        let (iu, iv) = (1.0 - u, 1.0 - v);
+        let features = [
+            (u_edge(hash(-1, 0)), iu * iu * v * iv),
+            (u_edge(h00), u * u * v * iv),
+            (v_edge(hash(0, -1)), iv * iv * u * iu),
+            (v_edge(h00), v * v * u * iu),
+            (corner(h00), squared(iu * iv)),
+            (corner(hash(1, 0)), squared(u * iv)),
+            (corner(hash(0, 1)), squared(iu * v)),
+            (corner(hash(1, 1)), squared(u * v)),
+        ];
+        let mut result = Vec3a::zero();
+        let mut total = 0.0;
+        for (i, &(color, weight)) in features.iter().enumerate() {
+            // Evaluate each color once.
+            if features[..i].iter().any(|&(c, _)| c == color) {
+                continue;
+            }
+            let weight: f32 = weight
+                + features[i + 1..]
+                    .iter()
+                    .filter(|&&(c, _)| c == color)
+                    .map(|&(_, w)| w)
+                    .sum::<f32>();
+            if weight <= 0.0 {
+                continue;
+            }
+            result += self.tiles[color].at_frequency(basis.d, frequency) * weight;
+            total += weight;
+        }
+        if total > 0.0 {
+            result / total
+        } else {
+            result
+        }
+    }
+
+    fn estimated_cost(&self) -> f32 {
+        // About two tiles are blended on average.
+        let tiles: f32 = self.tiles.iter().map(|tile| tile.estimated_cost()).sum();
+        0.2 + 2.0 * tiles / self.tiles.len() as f32
+    }
+
+    fn get_code(&self) -> String {
+        format!(
+            "wang({}, {:?}, {}, vec![{}], {})",
+            self.seed,
+            self.frequency,
+            self.axis.get_code(),
+            self.get_tiles_code(),
+            self.hasher.get_code()
+        )
+    }
+
+    fn get_basis_code(&self) -> String {
+        format!(
+            "wang_basis({}, {}, vec![{}], {})",
+            self.seed,
+            self.axis.get_code(),
+            self.get_tiles_code(),
+            self.hasher.get_code()
+        )
+    }
+}
@@ -239,6 +239,10 @@ impl<H: Hasher> Texture for Voronoi<H> {
         )
     }
 
+    fn estimated_cost(&self) -> f32 {
+        self.metric.voronoi_cost()
+    }
+
     fn get_code(&self) -> String {
         format!(
             "voronoi({}, {:?}, {}, {}, {}, {}, {}, {})",
@@ -351,6 +355,10 @@ impl<H: Hasher> Texture for Camo<H> {
         vec3a(d * color.x, d * color.y, d * color.z)
     }
 
+    fn estimated_cost(&self) -> f32 {
+        (0.75 + 0.3 * self.sharpness) * self.metric.voronoi_cost()
+    }
+
     fn get_code(&self) -> String {
         format!(
             "camo({}, {:?}, {}, {}, {}, {:?}, {:?}, {:?})",